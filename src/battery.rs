@@ -0,0 +1,282 @@
+use std::time::Duration;
+
+use zellij_tile::prelude::*;
+
+use crate::system::{render, SystemFiles};
+use crate::LinePart;
+
+const POWER_SUPPLY: &str = "sys/class/power_supply";
+// the files read from every supply
+pub const SUPPLY_FILES: [&str; 12] = [
+    "sys/class/power_supply/*/type",
+    "sys/class/power_supply/*/scope",
+    "sys/class/power_supply/*/present",
+    "sys/class/power_supply/*/status",
+    "sys/class/power_supply/*/capacity",
+    "sys/class/power_supply/*/energy_now",
+    "sys/class/power_supply/*/energy_full",
+    "sys/class/power_supply/*/power_now",
+    "sys/class/power_supply/*/voltage_now",
+    "sys/class/power_supply/*/charge_now",
+    "sys/class/power_supply/*/charge_full",
+    "sys/class/power_supply/*/current_now",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Charging,
+    Discharging,
+    // full or not charging while plugged in
+    Full,
+    Unknown,
+}
+
+impl Status {
+    fn parse(status: &str) -> Status {
+        match status {
+            "Charging" => Status::Charging,
+            "Discharging" => Status::Discharging,
+            "Full" | "Not charging" => Status::Full,
+            _ => Status::Unknown,
+        }
+    }
+}
+
+// one battery, amounts are in µWh and µW, or in µAh and µA when the battery only reports charge
+// and its voltage is unknown
+#[derive(Debug, Clone, Copy)]
+struct Supply {
+    status: Status,
+    capacity: Option<f64>,
+    now: Option<f64>,
+    full: Option<f64>,
+    rate: Option<f64>,
+}
+
+// all batteries added up
+#[derive(Debug, Clone, Copy)]
+struct BatteryState {
+    status: Status,
+    // 0 to 100
+    percent: f64,
+    // until empty when discharging, until full when charging
+    time_left: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+pub struct Battery {
+    // none without batteries
+    state: Option<BatteryState>,
+}
+
+impl Battery {
+    pub fn sample(&mut self, files: &SystemFiles) {
+        self.state = read_supplies(files);
+    }
+
+    pub fn render(
+        &self,
+        warn_percent: f64,
+        critical_percent: f64,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let state = self.state?;
+        let icon = match state.status {
+            Status::Charging => "⚡",
+            _ => "BAT",
+        };
+        let mut text = format!("{} {:.0}%", icon, state.percent);
+        if let Some(time_left) = state.time_left {
+            let minutes = time_left.as_secs() / 60;
+            text.push_str(&format!(" {}:{:02}", minutes / 60, minutes % 60));
+        }
+
+        // only a draining battery is a reason to worry
+        let is_draining = !matches!(state.status, Status::Charging | Status::Full);
+        let bg = if is_draining && state.percent <= critical_percent {
+            palette.exit_code_error.base
+        } else if is_draining && state.percent <= warn_percent {
+            palette.text_unselected.emphasis_0
+        } else {
+            palette.ribbon_unselected.background
+        };
+        Some(render(&text, bg, palette, separator))
+    }
+}
+
+fn read_supplies(files: &SystemFiles) -> Option<BatteryState> {
+    let supplies: Vec<Supply> = files
+        .folders(POWER_SUPPLY)
+        .into_iter()
+        .filter_map(|supply| read_supply(files, supply))
+        .collect();
+    if supplies.is_empty() {
+        return None;
+    }
+
+    let status = if supplies.iter().any(|s| s.status == Status::Charging) {
+        Status::Charging
+    } else if supplies.iter().any(|s| s.status == Status::Discharging) {
+        Status::Discharging
+    } else if supplies.iter().all(|s| s.status == Status::Full) {
+        Status::Full
+    } else {
+        Status::Unknown
+    };
+
+    // the amounts are only added up when every battery reports them
+    let sum = |amount: fn(&Supply) -> Option<f64>| supplies.iter().map(amount).sum::<Option<f64>>();
+    let (now, full, rate) = (sum(|s| s.now), sum(|s| s.full), sum(|s| s.rate));
+    let percent = match (now, full) {
+        (Some(now), Some(full)) if full > 0.0 => now / full * 100.0,
+        _ => {
+            let capacities: Vec<f64> = supplies.iter().filter_map(|s| s.capacity).collect();
+            if capacities.is_empty() {
+                return None;
+            }
+            capacities.iter().sum::<f64>() / capacities.len() as f64
+        }
+    };
+    let hours_left = match (status, now, full, rate) {
+        (_, _, _, Some(rate)) if rate <= 0.0 => None,
+        (Status::Discharging, Some(now), _, Some(rate)) => Some(now / rate),
+        (Status::Charging, Some(now), Some(full), Some(rate)) => Some((full - now).max(0.0) / rate),
+        _ => None,
+    };
+
+    Some(BatteryState {
+        status,
+        percent: percent.clamp(0.0, 100.0),
+        time_left: hours_left.and_then(|h| Duration::try_from_secs_f64(h * 3600.0).ok()),
+    })
+}
+
+fn read_supply(files: &SystemFiles, supply: &str) -> Option<Supply> {
+    let read = |name: &str| {
+        files
+            .read(&format!("{}/{}/{}", POWER_SUPPLY, supply, name))
+            .map(|v| v.trim().to_string())
+    };
+    let number = |name: &str| read(name).and_then(|v| v.parse::<f64>().ok());
+    if read("type")? != "Battery" {
+        return None;
+    }
+    // the batteries of mice and keyboards
+    if read("scope").as_deref() == Some("Device") {
+        return None;
+    }
+    if read("present").as_deref() == Some("0") {
+        return None;
+    }
+
+    let (now, full, rate) = match number("energy_now") {
+        Some(energy_now) => (Some(energy_now), number("energy_full"), number("power_now")),
+        None => {
+            // charge is converted to energy so batteries reporting either can be added up
+            let voltage = number("voltage_now").filter(|v| *v > 0.0).map(|v| v / 1e6);
+            let to_energy = |charge: Option<f64>| match voltage {
+                Some(voltage) => charge.map(|c| c * voltage),
+                None => charge,
+            };
+            (
+                to_energy(number("charge_now")),
+                to_energy(number("charge_full")),
+                to_energy(number("current_now")),
+            )
+        }
+    };
+    Some(Supply {
+        status: read("status")
+            .map(|s| Status::parse(&s))
+            .unwrap_or(Status::Unknown),
+        capacity: number("capacity"),
+        now,
+        full,
+        // some drivers report a negative rate while discharging
+        rate: rate.map(f64::abs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the supplies as printed by the files command, with the files of every supply
+    fn read(supplies: &[(&str, &[(&str, &str)])]) -> Option<BatteryState> {
+        let mut output = String::new();
+        for (supply, files) in supplies {
+            for (file, value) in *files {
+                output.push_str(&format!(
+                    "\u{1e}{}/{}/{}\n{}\n",
+                    POWER_SUPPLY, supply, file, value
+                ));
+            }
+        }
+        let mut files = SystemFiles::default();
+        files.handle_result(Some(0), output.as_bytes(), &[]);
+        read_supplies(&files)
+    }
+
+    #[test]
+    fn batteries_are_added_up() {
+        let state = read(&[
+            (
+                "BAT0",
+                &[
+                    ("type", "Battery"),
+                    ("status", "Discharging"),
+                    ("energy_now", "30000000"),
+                    ("energy_full", "50000000"),
+                    ("power_now", "10000000"),
+                ],
+            ),
+            (
+                "BAT1",
+                &[
+                    ("type", "Battery"),
+                    ("status", "Unknown"),
+                    ("energy_now", "10000000"),
+                    ("energy_full", "50000000"),
+                    ("power_now", "0"),
+                ],
+            ),
+            ("AC", &[("type", "Mains"), ("online", "0")]),
+        ])
+        .unwrap();
+        assert_eq!(state.status, Status::Discharging);
+        assert_eq!(state.percent, 40.0);
+        assert_eq!(state.time_left, Some(Duration::from_secs(4 * 3600)));
+    }
+
+    #[test]
+    fn charge_and_negative_current() {
+        let state = read(&[(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("charge_now", "2000000"),
+                ("charge_full", "4000000"),
+                ("current_now", "-1000000"),
+            ],
+        )])
+        .unwrap();
+        assert_eq!(state.percent, 50.0);
+        assert_eq!(state.time_left, Some(Duration::from_secs(2 * 3600)));
+    }
+
+    #[test]
+    fn device_batteries_are_ignored() {
+        let state = read(&[(
+            "hidpp_battery_0",
+            &[
+                ("type", "Battery"),
+                ("scope", "Device"),
+                ("status", "Discharging"),
+                ("capacity", "80"),
+            ],
+        )]);
+        assert!(state.is_none());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use zellij_tile::prelude::*;
+
+use crate::config::CommandConfig;
+use crate::line::strip_ansi;
+use crate::system::render;
+use crate::LinePart;
+
+// the exit code of `timeout` when the command took too long
+const TIMEOUT_EXIT_CODE: i32 = 124;
+// key of the run_command context holding the name of the command segment
+pub const COMMAND_CONTEXT_KEY: &str = "command_segment";
+
+#[derive(Debug)]
+struct CommandOutput {
+    text: String,
+    is_error: bool,
+}
+
+#[derive(Debug)]
+pub struct CommandSegment {
+    config: CommandConfig,
+    output: Option<CommandOutput>,
+    // a command is never started again before its last run is done
+    is_running: bool,
+    last_run_at: Option<Instant>,
+}
+
+impl CommandSegment {
+    pub fn new(config: CommandConfig) -> Self {
+        CommandSegment {
+            config,
+            output: None,
+            is_running: false,
+            last_run_at: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    // the segment id used to hide it
+    pub fn id(&self) -> String {
+        format!("command_{}", self.config.name)
+    }
+
+    pub fn uses_cwd(&self) -> bool {
+        self.config.command.contains("{cwd}")
+    }
+
+    // runs the command if it's due
+    pub fn tick(&mut self, now: Instant, cwd: Option<&Path>) {
+        let is_due = match self.last_run_at {
+            Some(last_run_at) => now.duration_since(last_run_at) >= self.config.interval,
+            None => true,
+        };
+        if self.is_running || !is_due {
+            return;
+        }
+        self.is_running = true;
+        self.last_run_at = Some(now);
+
+        // commands run in the plugin's cwd, which is the directory zellij was started in, until
+        // the cwd of the focused pane is known
+        let cwd = cwd
+            .map(|cwd| shell_quote(&cwd.to_string_lossy()))
+            .unwrap_or_else(|| ".".to_string());
+        let command = self.config.command.replace("{cwd}", &cwd);
+        // `timeout` isn't available everywhere, the command runs without it in that case
+        let script = format!(
+            "if command -v timeout >/dev/null 2>&1; then exec timeout {} sh -c \"$0\"; else exec sh -c \"$0\"; fi",
+            self.config.timeout.as_secs_f64()
+        );
+        let mut context = BTreeMap::new();
+        context.insert(COMMAND_CONTEXT_KEY.to_string(), self.config.name.clone());
+        run_command(&["sh", "-c", &script, &command], context);
+    }
+
+    // returns whether the output changed
+    pub fn handle_result(&mut self, exit_code: Option<i32>, stdout: &[u8], stderr: &[u8]) -> bool {
+        self.is_running = false;
+        let output = match exit_code {
+            Some(0) => CommandOutput {
+                text: first_line(stdout),
+                is_error: false,
+            },
+            Some(TIMEOUT_EXIT_CODE) => CommandOutput {
+                text: "timeout".to_string(),
+                is_error: true,
+            },
+            _ => {
+                let stderr = first_line(stderr);
+                CommandOutput {
+                    text: if stderr.is_empty() {
+                        first_line(stdout)
+                    } else {
+                        stderr
+                    },
+                    is_error: true,
+                }
+            }
+        };
+        let changed = self
+            .output
+            .as_ref()
+            .map(|o| o.text != output.text || o.is_error != output.is_error)
+            .unwrap_or(true);
+        self.output = Some(output);
+        changed
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let output = self.output.as_ref().filter(|o| !o.text.is_empty())?;
+        let text = truncate(&output.text, self.config.max_width);
+        let bg = if output.is_error {
+            palette.exit_code_error.base
+        } else {
+            palette.ribbon_unselected.background
+        };
+        Some(render(&text, bg, palette, separator))
+    }
+}
+
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+fn first_line(output: &[u8]) -> String {
+    let output = String::from_utf8_lossy(output);
+    let output = strip_ansi(output.lines().find(|l| !l.trim().is_empty()).unwrap_or(""));
+    output.trim().to_string()
+}
+
+// cuts the text to max_width columns, ending it with an ellipsis if it was cut
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::format::StrftimeItems;
+use zellij_tile::prelude::{PaletteColor, PermissionType};
+
+use crate::pomodoro::PomodoroConfig;
+use crate::sun::{Location, NightColors};
+use crate::system::{Display, Thresholds};
+
+// how many days ahead segments look at most, huge values would overflow the dates
+const MAX_DAYS: usize = 3660;
+
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    // swap layout names in the order they appear in the layout file, zellij doesn't tell us how
+    // many swap layouts a tab has so this is needed to show the position of the active one
+    pub swap_layouts: Vec<String>,
+    pub new_tab_button: bool,
+    // dragging a tab onto another one moves it, through the zellij cli
+    pub drag_tabs: bool,
+    // a built-in layout name or the path to a layout file, the default layout is used otherwise
+    pub new_tab_layout: Option<String>,
+    // only used with the default layout, layout files set their own cwd
+    pub new_tab_cwd: Option<String>,
+    // scrolling up goes to the next tab unless reversed
+    pub scroll_reverse: bool,
+    pub scroll_wrap: bool,
+    pub scroll_order: ScrollOrder,
+    // strftime with the extra placeholders of date::format_date
+    pub time_format: String,
+    // shown instead of the time while the clock is hovered, or after clicking it
+    pub time_alt_format: Option<String>,
+    pub time_click: TimeClick,
+    // ids of the parts of the bar that aren't rendered, listed in the readme
+    pub hidden_segments: BTreeSet<String>,
+    // how many notifications are kept in the history
+    pub notification_history: usize,
+    // progress items are removed when they aren't updated for this long
+    pub progress_timeout: Duration,
+    // segments showing the output of shell commands, defined as `command_<name> "<command>"`
+    pub commands: Vec<CommandConfig>,
+    // segments showing a value read from a file whenever it changes, defined as
+    // `file_<name> "<absolute path>"`
+    pub files: Vec<FileConfig>,
+    // shows the current kubernetes context and namespace
+    pub kube: bool,
+    // KUBECONFIG, or ~/.kube/config without it, is read otherwise
+    pub kube_config: Option<PathBuf>,
+    // contexts matching this regex are shown in red
+    pub kube_production: Option<String>,
+    pub pomodoro: bool,
+    pub pomodoro_config: PomodoroConfig,
+    // the length of the countdowns started by right clicking the clock
+    pub timer_countdown: Duration,
+    // holiday files by region, defined as `holidays_<region> "<path>"`, icalendar files or
+    // `YYYY-MM-DD name` lines
+    pub holidays: Vec<(String, PathBuf)>,
+    // upcoming holidays are shown this many days ahead
+    pub holiday_days: i64,
+    // shows the next event of an icalendar file with a countdown
+    pub meeting_calendar: Option<PathBuf>,
+    // how many days ahead events are looked for
+    pub meeting_days: i64,
+    // the countdown turns red this long before the event starts
+    pub meeting_warn: Duration,
+    // shows the branch and status of the git repository the focused pane is in
+    pub git_status: bool,
+    // how often the git status is refreshed when the focused pane doesn't change
+    pub git_interval: Duration,
+    // system resource segments, shown as text or as a sparkline of the last samples
+    pub cpu: Option<Display>,
+    pub memory: Option<Display>,
+    pub load: Option<Display>,
+    pub uptime: bool,
+    // hidden when there's no battery
+    pub battery: bool,
+    // a draining battery is shown in the warning color at this percentage, and in red at the
+    // critical one
+    pub battery_warn: f64,
+    pub battery_critical: f64,
+    // mount points whose usage is shown, `df` is run with them
+    pub disk: Vec<String>,
+    pub disk_interval: Duration,
+    // in percent used
+    pub disk_thresholds: Thresholds,
+    // the hottest thermal zone, or the hottest one of this type, eg. `x86_pkg_temp`
+    pub thermal: bool,
+    pub thermal_zone: Option<String>,
+    // in °C
+    pub thermal_thresholds: Thresholds,
+    pub network: Option<Display>,
+    // the busiest interface is shown otherwise
+    pub network_interface: Option<String>,
+    // rates are averaged over this long
+    pub network_window: Duration,
+    // how often /proc and /sys are read
+    pub system_interval: Duration,
+    // the directory /proc and /sys are read from
+    pub system_root: PathBuf,
+    // shows the next sunrise or sunset
+    pub location: Option<Location>,
+    // adds the moon phase to the sunrise and sunset
+    pub moon_phase: bool,
+    // the night colors replace the theme's between sunset and sunrise
+    pub day_night_theme: bool,
+    pub night_colors: NightColors,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandConfig {
+    pub name: String,
+    // run with `sh -c`, `{cwd}` is replaced with the cwd of the focused pane
+    pub command: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    // the output is cut to this many columns
+    pub max_width: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileConfig {
+    pub name: String,
+    pub path: PathBuf,
+    // the first capture group, or the whole match, is shown
+    pub regex: Option<String>,
+    // starting from 1, the first line that isn't empty is shown without regex or line
+    pub line: Option<usize>,
+    // `{value}` is replaced with the extracted value
+    pub template: String,
+    // the value is cut to this many columns
+    pub max_width: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollOrder {
+    #[default]
+    Position,
+    // most recently used first
+    Mru,
+}
+
+// what clicking the clock does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeClick {
+    // starts a stopwatch, or a countdown with a right click
+    #[default]
+    Timer,
+    // switches between the time and the alternate calendar
+    Calendar,
+}
+
+impl Config {
+    pub fn from_configuration(configuration: &BTreeMap<String, String>) -> Self {
+        let swap_layouts = configuration
+            .get("swap_layouts")
+            .map(|names| names.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+        let holidays: Vec<(String, PathBuf)> = configuration
+            .iter()
+            .filter_map(|(key, path)| {
+                let region = key.strip_prefix("holidays_")?;
+                Some((region.to_string(), PathBuf::from(path)))
+            })
+            .collect();
+        // the holidays are shown by default once there are some
+        let default_time_format = if holidays.is_empty() {
+            " %H:%M:%S %A "
+        } else {
+            " %H:%M:%S %A {holiday} "
+        };
+        Config {
+            swap_layouts,
+            new_tab_button: parse_bool(configuration, "new_tab_button"),
+            drag_tabs: parse_bool(configuration, "drag_tabs"),
+            new_tab_layout: configuration.get("new_tab_layout").cloned(),
+            new_tab_cwd: configuration.get("new_tab_cwd").cloned(),
+            scroll_reverse: parse_bool(configuration, "scroll_reverse"),
+            scroll_wrap: parse_bool(configuration, "scroll_wrap"),
+            scroll_order: match configuration.get("scroll_order").map(|s| s.as_str()) {
+                Some("mru") => ScrollOrder::Mru,
+                _ => ScrollOrder::Position,
+            },
+            time_format: parse_time_format(configuration, "time_format")
+                .unwrap_or_else(|| default_time_format.to_string()),
+            time_alt_format: parse_time_format(configuration, "time_alt_format"),
+            time_click: match configuration.get("time_click").map(|s| s.as_str()) {
+                Some("calendar") => TimeClick::Calendar,
+                _ => TimeClick::Timer,
+            },
+            hidden_segments: configuration
+                .get("hidden_segments")
+                .map(|ids| ids.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            notification_history: parse_usize(configuration, "notification_history").unwrap_or(50),
+            progress_timeout: parse_seconds(configuration, "progress_timeout")
+                .unwrap_or(Duration::from_secs(60)),
+            commands: parse_commands(configuration),
+            files: parse_files(configuration),
+            pomodoro: parse_bool(configuration, "pomodoro"),
+            pomodoro_config: PomodoroConfig {
+                work: parse_minutes(configuration, "pomodoro_work")
+                    .unwrap_or(Duration::from_secs(25 * 60)),
+                short_break: parse_minutes(configuration, "pomodoro_short_break")
+                    .unwrap_or(Duration::from_secs(5 * 60)),
+                long_break: parse_minutes(configuration, "pomodoro_long_break")
+                    .unwrap_or(Duration::from_secs(15 * 60)),
+                long_break_every: parse_usize(configuration, "pomodoro_long_break_every")
+                    .unwrap_or(4),
+            },
+            timer_countdown: parse_minutes(configuration, "timer_countdown")
+                .unwrap_or(Duration::from_secs(5 * 60)),
+            holidays,
+            holiday_days: parse_days(configuration, "holiday_days").unwrap_or(30),
+            meeting_calendar: configuration.get("meeting_calendar").map(PathBuf::from),
+            meeting_days: parse_days(configuration, "meeting_days").unwrap_or(7),
+            meeting_warn: parse_seconds(configuration, "meeting_warn")
+                .unwrap_or(Duration::from_secs(60)),
+            kube: parse_bool(configuration, "kube"),
+            kube_config: configuration.get("kube_config").map(PathBuf::from),
+            kube_production: configuration.get("kube_production").cloned(),
+            git_status: parse_bool(configuration, "git_status"),
+            git_interval: parse_seconds(configuration, "git_interval")
+                .unwrap_or(Duration::from_secs(5)),
+            cpu: parse_display(configuration, "cpu"),
+            memory: parse_display(configuration, "memory"),
+            load: parse_display(configuration, "load"),
+            uptime: parse_bool(configuration, "uptime"),
+            battery: parse_bool(configuration, "battery"),
+            battery_warn: parse_percent(configuration, "battery_warn").unwrap_or(30.0),
+            battery_critical: parse_percent(configuration, "battery_critical").unwrap_or(15.0),
+            disk: configuration
+                .get("disk")
+                .map(|mount_points| mount_points.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            disk_interval: parse_seconds(configuration, "disk_interval")
+                .unwrap_or(Duration::from_secs(30)),
+            disk_thresholds: Thresholds {
+                warn: parse_percent(configuration, "disk_warn").unwrap_or(80.0),
+                critical: parse_percent(configuration, "disk_critical").unwrap_or(90.0),
+            },
+            thermal: parse_bool(configuration, "thermal"),
+            thermal_zone: configuration.get("thermal_zone").cloned(),
+            thermal_thresholds: Thresholds {
+                warn: parse_f64(configuration, "thermal_warn").unwrap_or(75.0),
+                critical: parse_f64(configuration, "thermal_critical").unwrap_or(90.0),
+            },
+            network: parse_display(configuration, "network"),
+            network_interface: configuration.get("network_interface").cloned(),
+            network_window: parse_seconds(configuration, "network_window")
+                .unwrap_or(Duration::from_secs(3)),
+            system_interval: parse_seconds(configuration, "system_interval")
+                .unwrap_or(Duration::from_secs(2)),
+            system_root: configuration
+                .get("system_root")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("/")),
+            location: parse_location(configuration),
+            moon_phase: parse_bool(configuration, "moon_phase"),
+            day_night_theme: parse_bool(configuration, "day_night_theme"),
+            night_colors: parse_night_colors(configuration),
+        }
+    }
+
+    // only what the enabled segments need is asked for
+    pub fn permissions(&self) -> Vec<PermissionType> {
+        let mut permissions = vec![
+            PermissionType::ReadApplicationState,
+            PermissionType::ChangeApplicationState,
+            PermissionType::ReadCliPipes,
+        ];
+        if self.reads_host_files() {
+            permissions.push(PermissionType::FullHdAccess);
+        }
+        if self.runs_commands() {
+            permissions.push(PermissionType::RunCommands);
+        }
+        permissions
+    }
+
+    fn runs_commands(&self) -> bool {
+        self.drag_tabs
+            || !self.commands.is_empty()
+            || self.git_status
+            || !self.disk.is_empty()
+            || self.kube
+            || self.shows_system()
+    }
+
+    // files outside of the directory zellij was started in are only readable once the host folder
+    // is changed
+    pub fn reads_host_files(&self) -> bool {
+        !self.files.is_empty()
+            || self.kube
+            || self.meeting_calendar.is_some()
+            || !self.holidays.is_empty()
+    }
+
+    pub fn shows_system(&self) -> bool {
+        self.cpu.is_some()
+            || self.memory.is_some()
+            || self.load.is_some()
+            || self.uptime
+            || self.battery
+            || self.network.is_some()
+            || self.thermal
+    }
+
+    pub fn shows(&self, segment_id: &str) -> bool {
+        !self.hidden_segments.contains(segment_id)
+    }
+}
+
+// every `command_<name>` key, with its optional `command_<name>_interval`,
+// `command_<name>_timeout` and `command_<name>_max_width` settings
+fn parse_commands(configuration: &BTreeMap<String, String>) -> Vec<CommandConfig> {
+    const SETTINGS: [&str; 3] = ["_interval", "_timeout", "_max_width"];
+    configuration
+        .iter()
+        .filter_map(|(key, command)| {
+            let name = key.strip_prefix("command_")?;
+            if SETTINGS.iter().any(|s| name.ends_with(s)) {
+                return None;
+            }
+            let setting = |s: &str| format!("{}{}", key, s);
+            Some(CommandConfig {
+                name: name.to_string(),
+                command: command.clone(),
+                interval: parse_seconds(configuration, &setting("_interval"))
+                    .unwrap_or(Duration::from_secs(10)),
+                timeout: parse_seconds(configuration, &setting("_timeout"))
+                    .unwrap_or(Duration::from_secs(5)),
+                max_width: parse_usize(configuration, &setting("_max_width")).unwrap_or(30),
+            })
+        })
+        .collect()
+}
+
+// every `file_<name>` key, with its optional `file_<name>_regex`, `file_<name>_line`,
+// `file_<name>_template` and `file_<name>_max_width` settings
+fn parse_files(configuration: &BTreeMap<String, String>) -> Vec<FileConfig> {
+    const SETTINGS: [&str; 4] = ["_regex", "_line", "_template", "_max_width"];
+    configuration
+        .iter()
+        .filter_map(|(key, path)| {
+            let name = key.strip_prefix("file_")?;
+            if SETTINGS.iter().any(|s| name.ends_with(s)) {
+                return None;
+            }
+            let setting = |s: &str| format!("{}{}", key, s);
+            Some(FileConfig {
+                name: name.to_string(),
+                path: PathBuf::from(path),
+                regex: configuration.get(&setting("_regex")).cloned(),
+                line: parse_usize(configuration, &setting("_line")),
+                template: configuration
+                    .get(&setting("_template"))
+                    .cloned()
+                    .unwrap_or_else(|| "{value}".to_string()),
+                max_width: parse_usize(configuration, &setting("_max_width")).unwrap_or(30),
+            })
+        })
+        .collect()
+}
+
+fn parse_seconds(configuration: &BTreeMap<String, String>, key: &str) -> Option<Duration> {
+    configuration
+        .get(key)
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .and_then(|v| Duration::try_from_secs_f64(v).ok())
+}
+
+fn parse_minutes(configuration: &BTreeMap<String, String>, key: &str) -> Option<Duration> {
+    parse_seconds(configuration, key).and_then(|minutes| minutes.checked_mul(60))
+}
+
+fn parse_usize(configuration: &BTreeMap<String, String>, key: &str) -> Option<usize> {
+    configuration.get(key).and_then(|v| v.parse().ok())
+}
+
+fn parse_days(configuration: &BTreeMap<String, String>, key: &str) -> Option<i64> {
+    parse_usize(configuration, key).map(|days| days.min(MAX_DAYS) as i64)
+}
+
+fn parse_percent(configuration: &BTreeMap<String, String>, key: &str) -> Option<f64> {
+    configuration
+        .get(key)
+        .and_then(|v| v.trim_end_matches('%').parse::<f64>().ok())
+        .filter(|v| (0.0..=100.0).contains(v))
+}
+
+fn parse_f64(configuration: &BTreeMap<String, String>, key: &str) -> Option<f64> {
+    configuration
+        .get(key)
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite())
+}
+
+fn parse_location(configuration: &BTreeMap<String, String>) -> Option<Location> {
+    let latitude = parse_f64(configuration, "latitude").filter(|l| (-90.0..=90.0).contains(l))?;
+    let longitude =
+        parse_f64(configuration, "longitude").filter(|l| (-180.0..=180.0).contains(l))?;
+    Some(Location {
+        latitude,
+        longitude,
+    })
+}
+
+// `night_background`, `night_ribbon_selected`, `night_ribbon_unselected` and `night_text`, as
+// #rrggbb or a 256 color number
+fn parse_night_colors(configuration: &BTreeMap<String, String>) -> NightColors {
+    let defaults = NightColors::default();
+    let color = |key: &str, default: PaletteColor| {
+        configuration
+            .get(key)
+            .and_then(|c| parse_color(c))
+            .unwrap_or(default)
+    };
+    NightColors {
+        background: color("night_background", defaults.background),
+        ribbon_selected: color("night_ribbon_selected", defaults.ribbon_selected),
+        ribbon_unselected: color("night_ribbon_unselected", defaults.ribbon_unselected),
+        text: color("night_text", defaults.text),
+    }
+}
+
+fn parse_color(color: &str) -> Option<PaletteColor> {
+    let Some(hex) = color.strip_prefix('#') else {
+        return color.parse().ok().map(PaletteColor::EightBit);
+    };
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(PaletteColor::Rgb((channel(0)?, channel(2)?, channel(4)?)))
+}
+
+fn parse_display(configuration: &BTreeMap<String, String>, key: &str) -> Option<Display> {
+    configuration.get(key).and_then(|v| Display::parse(v))
+}
+
+// invalid formats would break rendering, they're left out
+fn parse_time_format(configuration: &BTreeMap<String, String>, key: &str) -> Option<String> {
+    let format = configuration.get(key)?;
+    match StrftimeItems::new(format).parse() {
+        Ok(_) => Some(format.clone()),
+        Err(e) => {
+            eprintln!("Invalid {}: {}", key, e);
+            None
+        }
+    }
+}
+
+fn parse_bool(configuration: &BTreeMap<String, String>, key: &str) -> bool {
+    configuration.get(key).map(|v| v == "true").unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration(key: &str, value: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([(key.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn durations_that_overflow_are_ignored() {
+        assert_eq!(
+            parse_seconds(&configuration("interval", "1e30"), "interval"),
+            None
+        );
+        assert_eq!(parse_minutes(&configuration("work", "1e18"), "work"), None);
+        assert_eq!(
+            parse_minutes(&configuration("work", "1.5"), "work"),
+            Some(Duration::from_secs(90))
+        );
+    }
+
+    #[test]
+    fn days_are_limited() {
+        let config = Config::from_configuration(&configuration("meeting_days", "99999999999999"));
+        assert_eq!(config.meeting_days, MAX_DAYS as i64);
+    }
+
+    #[test]
+    fn holidays_are_in_the_default_time_format() {
+        let config = Config::from_configuration(&BTreeMap::new());
+        assert_eq!(config.time_format, " %H:%M:%S %A ");
+        let config = Config::from_configuration(&configuration("holidays_jp", "/jp.ics"));
+        assert_eq!(config.time_format, " %H:%M:%S %A {holiday} ");
+    }
+
+    #[test]
+    fn permissions_follow_the_configuration() {
+        let permissions = Config::from_configuration(&BTreeMap::new()).permissions();
+        assert!(!permissions.contains(&PermissionType::FullHdAccess));
+        assert!(!permissions.contains(&PermissionType::RunCommands));
+
+        let permissions =
+            Config::from_configuration(&configuration("uptime", "true")).permissions();
+        assert!(!permissions.contains(&PermissionType::FullHdAccess));
+        assert!(permissions.contains(&PermissionType::RunCommands));
+
+        let permissions =
+            Config::from_configuration(&configuration("file_status", "/home/me/.status"))
+                .permissions();
+        assert!(permissions.contains(&PermissionType::FullHdAccess));
+        assert!(!permissions.contains(&PermissionType::RunCommands));
+
+        let permissions =
+            Config::from_configuration(&configuration("drag_tabs", "true")).permissions();
+        assert!(!permissions.contains(&PermissionType::FullHdAccess));
+        assert!(permissions.contains(&PermissionType::RunCommands));
+    }
+}
@@ -0,0 +1,236 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zellij_tile::prelude::*;
+
+// plugins aren't told the cwd of panes, it's read from the session layout instead, which is
+// requested again when the focused pane changes (shells usually set the title to their cwd)
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+// cwd changes that don't change the pane title are picked up after this long
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct CwdTracker {
+    cwd: Option<PathBuf>,
+    // id and title of the focused pane
+    focused_pane: Option<(u32, String)>,
+    is_stale: bool,
+    requested_at: Option<Instant>,
+}
+
+impl CwdTracker {
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    pub fn update_panes(&mut self, panes: &PaneManifest, active_tab: Option<&TabInfo>) {
+        let focused_pane = active_tab
+            .and_then(|tab| {
+                let panes = panes.panes.get(&tab.position)?;
+                let mut focused = panes
+                    .iter()
+                    .filter(|p| p.is_focused && !p.is_plugin && p.is_selectable);
+                // floating and tiled panes both have a focused pane
+                if tab.are_floating_panes_visible {
+                    focused.find(|p| p.is_floating)
+                } else {
+                    focused.find(|p| !p.is_floating)
+                }
+            })
+            .map(|p| (p.id, p.title.clone()));
+        if focused_pane != self.focused_pane {
+            self.focused_pane = focused_pane;
+            self.is_stale = true;
+        }
+    }
+
+    // requests the session layout if the cwd might have changed
+    pub fn tick(&mut self, now: Instant) {
+        let since_request = self.requested_at.map(|r| now.duration_since(r));
+        let should_request = match since_request {
+            None => true,
+            Some(since_request) => {
+                (self.is_stale && since_request >= MIN_REQUEST_INTERVAL)
+                    || since_request >= REFRESH_INTERVAL
+            }
+        };
+        if should_request {
+            self.is_stale = false;
+            self.requested_at = Some(now);
+            dump_session_layout();
+        }
+    }
+
+    // returns whether the cwd changed
+    pub fn update_layout(&mut self, layout: &str) -> bool {
+        let cwd = focused_pane_cwd(layout);
+        if cwd.is_some() && cwd != self.cwd {
+            self.cwd = cwd;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// the cwd of the focused pane of the focused tab in a serialized session layout, pane and tab
+// cwds are relative to the cwd of their parent. Like in update_panes, the focused floating pane
+// is only used when the floating panes are visible
+fn focused_pane_cwd(layout: &str) -> Option<PathBuf> {
+    let mut global_cwd = None;
+    let mut tab_cwd = None;
+    let mut in_focused_tab = false;
+    let mut are_floating_panes_visible = false;
+    // the depth of the floating_panes block of the focused tab while in it
+    let mut floating_panes_depth = None;
+    let mut tiled_cwd = None;
+    let mut floating_cwd = None;
+    let mut depth = 0;
+    for line in layout.lines().map(str::trim) {
+        if line == "}" {
+            depth -= 1;
+            if floating_panes_depth == Some(depth) {
+                floating_panes_depth = None;
+            }
+            if depth <= 1 && in_focused_tab {
+                break;
+            }
+            continue;
+        }
+        if depth == 1 {
+            if let Some(cwd) = line.strip_prefix("cwd ") {
+                global_cwd = parse_string(cwd.trim()).map(|(cwd, _)| PathBuf::from(cwd));
+            } else if line.starts_with("tab ") || line == "tab {" {
+                in_focused_tab = prop(line, "focus").as_deref() == Some("true");
+                if in_focused_tab {
+                    tab_cwd = prop(line, "cwd").map(PathBuf::from);
+                    are_floating_panes_visible =
+                        prop(line, "hide_floating_panes").as_deref() != Some("true");
+                }
+            }
+        } else if in_focused_tab && line == "floating_panes {" {
+            floating_panes_depth = Some(depth);
+        } else if in_focused_tab
+            && (line.starts_with("pane ") || line == "pane")
+            && prop(line, "focus").as_deref() == Some("true")
+        {
+            let parent_cwd = join_cwd(global_cwd.as_deref(), tab_cwd.as_deref());
+            let pane_cwd = prop(line, "cwd").map(PathBuf::from);
+            let cwd = if floating_panes_depth.is_some() {
+                &mut floating_cwd
+            } else {
+                &mut tiled_cwd
+            };
+            if cwd.is_none() {
+                *cwd = join_cwd(parent_cwd.as_deref(), pane_cwd.as_deref());
+            }
+        }
+        if line.ends_with('{') {
+            depth += 1;
+        }
+    }
+    if are_floating_panes_visible {
+        floating_cwd.or(tiled_cwd)
+    } else {
+        tiled_cwd
+    }
+}
+
+fn join_cwd(parent: Option<&Path>, child: Option<&Path>) -> Option<PathBuf> {
+    match (parent, child) {
+        (Some(parent), Some(child)) => Some(parent.join(child)),
+        (parent, child) => child.or(parent).map(Path::to_path_buf),
+    }
+}
+
+// the value of a `key=value` property in a kdl node line
+fn prop(line: &str, key: &str) -> Option<String> {
+    let mut rest = line;
+    while let Some(index) = rest.find(key) {
+        let is_start_of_prop = rest[..index].ends_with(' ');
+        rest = &rest[index + key.len()..];
+        if !is_start_of_prop {
+            continue;
+        }
+        if let Some(value) = rest.strip_prefix('=') {
+            if value.starts_with('"') {
+                return parse_string(value).map(|(value, _)| value);
+            }
+            let end = value
+                .find(|c: char| c.is_whitespace() || c == '{')
+                .unwrap_or(value.len());
+            return Some(value[..end].to_string());
+        }
+    }
+    None
+}
+
+// a quoted kdl string at the start of text, and the text after it
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &text[index + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = r#"layout {
+    cwd "/home/me"
+    tab name="docs" cwd="docs" {
+        pane focus=true cwd="api"
+    }
+    tab name="web" focus=true cwd="src/web" HIDE {
+        pane split_direction="vertical" {
+            pane cwd="server"
+            pane focus=true cwd="client"
+        }
+        floating_panes {
+            pane focus=true cwd="/tmp/scratch" {
+                x 10
+            }
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn focused_tiled_pane_when_floating_panes_are_hidden() {
+        let layout = LAYOUT.replace("HIDE", "hide_floating_panes=true");
+        assert_eq!(
+            focused_pane_cwd(&layout),
+            Some(PathBuf::from("/home/me/src/web/client"))
+        );
+    }
+
+    #[test]
+    fn focused_floating_pane_when_floating_panes_are_visible() {
+        let layout = LAYOUT.replace(" HIDE", "");
+        assert_eq!(
+            focused_pane_cwd(&layout),
+            Some(PathBuf::from("/tmp/scratch"))
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(
+            prop(r#"pane name="a \"b\"" cwd="/x y" focus=true {"#, "cwd"),
+            Some("/x y".to_string())
+        );
+        assert_eq!(prop("pane focus=true {", "focus"), Some("true".to_string()));
+        assert_eq!(prop("pane nofocus=true", "focus"), None);
+    }
+}
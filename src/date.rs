@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, Locale, NaiveDate, Weekday};
+use chrono_tz::Tz;
+
+use crate::holiday::Holidays;
+
+// japanese eras, from the latest, with the day they started
+const ERAS: [(&str, i32, u32, u32); 5] = [
+    ("令和", 2019, 5, 1),
+    ("平成", 1989, 1, 8),
+    ("昭和", 1926, 12, 25),
+    ("大正", 1912, 7, 30),
+    ("明治", 1868, 10, 23),
+];
+
+// strftime, with these placeholders replaced first:
+// {iso_week}    the iso week with its year, eg. 2026-W42
+// {era}         the year in the japanese calendar, eg. 令和8年
+// {quarter}     1 to 4
+// {day_of_year} 1 to 366
+// {weekend_in}  days until saturday, 0 on weekends
+// {holiday}     today's holiday, or the next one with its date, removed with the space before it
+//               when there's none
+// {business_days:2026-12-25} business days after today until the date, without weekends and
+//               holidays
+pub fn format_date(now: DateTime<Tz>, format: &str, holidays: &Holidays) -> String {
+    let today = now.date_naive();
+    let week = now.iso_week();
+    let format = format
+        .replace(
+            "{iso_week}",
+            &format!("{}-W{:02}", week.year(), week.week()),
+        )
+        .replace("{era}", &escape(&japanese_year(today)))
+        .replace("{quarter}", &now.quarter().to_string())
+        .replace("{day_of_year}", &now.ordinal().to_string())
+        .replace(
+            "{weekend_in}",
+            &days_until_weekend(now.weekday()).to_string(),
+        );
+    let format = match holidays.describe(today) {
+        Some(holiday) => format.replace("{holiday}", &escape(&holiday)),
+        None => format.replace(" {holiday}", "").replace("{holiday}", ""),
+    };
+    let format = replace_business_days(&format, today, holidays);
+    // the format is checked when it's configured and the values are escaped, nothing is shown
+    // rather than the format if it's still invalid
+    let mut text = String::new();
+    match write!(text, "{}", now.format_localized(&format, Locale::ja_JP)) {
+        Ok(()) => text,
+        Err(_) => String::new(),
+    }
+}
+
+// values put in the format are shown as they are, not read as strftime
+fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+// each `{business_days:YYYY-MM-DD}`, left as it is when the date is invalid
+fn replace_business_days(format: &str, today: NaiveDate, holidays: &Holidays) -> String {
+    const PREFIX: &str = "{business_days:";
+    let mut replaced = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find(PREFIX) {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        replaced.push_str(&rest[..start]);
+        let date = &rest[start + PREFIX.len()..end];
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => {
+                let days = holidays.business_days_until(today, date).to_string();
+                replaced.push_str(&escape(&days));
+            }
+            Err(_) => replaced.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+// the first year of an era is 元年
+fn japanese_year(date: NaiveDate) -> String {
+    let era = ERAS.iter().find(|(_, year, month, day)| {
+        NaiveDate::from_ymd_opt(*year, *month, *day).is_some_and(|start| date >= start)
+    });
+    match era {
+        Some((name, year, ..)) if date.year() == *year => format!("{}元年", name),
+        Some((name, year, ..)) => format!("{}{}年", name, date.year() - year + 1),
+        None => format!("{}年", date.year()),
+    }
+}
+
+fn days_until_weekend(weekday: Weekday) -> u32 {
+    Weekday::Sat
+        .num_days_from_monday()
+        .saturating_sub(weekday.num_days_from_monday())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn format(date: NaiveDate, format: &str, holidays: &Holidays) -> String {
+        let now = Tz::Asia__Tokyo
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap();
+        format_date(now, format, holidays)
+    }
+
+    #[test]
+    fn era_boundaries() {
+        assert_eq!(japanese_year(date(2019, 4, 30)), "平成31年");
+        assert_eq!(japanese_year(date(2019, 5, 1)), "令和元年");
+        assert_eq!(japanese_year(date(2026, 1, 1)), "令和8年");
+        assert_eq!(japanese_year(date(1800, 1, 1)), "1800年");
+    }
+
+    #[test]
+    fn iso_week_years() {
+        // friday, in the last week of the previous year
+        let holidays = Holidays::default();
+        assert_eq!(
+            format(date(2027, 1, 1), "{iso_week}", &holidays),
+            "2026-W53"
+        );
+        // monday, in the first week of the next year
+        assert_eq!(
+            format(date(2024, 12, 30), "{iso_week}", &holidays),
+            "2025-W01"
+        );
+        assert_eq!(
+            format(date(2026, 1, 1), "{iso_week}", &holidays),
+            "2026-W01"
+        );
+    }
+
+    #[test]
+    fn values_are_not_read_as_strftime() {
+        let mut holidays = Holidays::new(vec![("jp".to_string(), PathBuf::new())], 30);
+        holidays.add(
+            "jp",
+            "2026-01-01 Sale 100%\n2026-01-02 50%d off\n",
+            date(2026, 1, 1),
+        );
+        assert_eq!(
+            format(date(2026, 1, 1), "%Y {holiday}", &holidays),
+            "2026 Sale 100%"
+        );
+        assert_eq!(
+            format(date(2026, 1, 2), "{holiday} %d", &holidays),
+            "50%d off 02"
+        );
+    }
+
+    #[test]
+    fn invalid_business_days_dates_are_left() {
+        let format = "{business_days:2026-13-01} {business_days:soon} {business_days:2026-01-01";
+        assert_eq!(
+            replace_business_days(format, date(2026, 1, 1), &Holidays::default()),
+            format
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::system::Thresholds;
+use crate::LinePart;
+
+// key of the run_command context of df runs
+pub const DISK_CONTEXT_KEY: &str = "disk_usage";
+
+#[derive(Debug, Clone)]
+struct MountUsage {
+    mount_point: String,
+    // 0 to 100
+    percent: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    usage: Vec<MountUsage>,
+    // df is never started again before its last run is done
+    is_running: bool,
+    last_run_at: Option<Instant>,
+}
+
+impl DiskUsage {
+    // runs df if the last run is older than interval
+    pub fn tick(&mut self, now: Instant, interval: Duration, mount_points: &[String]) {
+        let is_due = match self.last_run_at {
+            Some(last_run_at) => now.duration_since(last_run_at) >= interval,
+            None => true,
+        };
+        if self.is_running || !is_due || mount_points.is_empty() {
+            return;
+        }
+        self.is_running = true;
+        self.last_run_at = Some(now);
+
+        let mut command = vec!["df", "-P"];
+        command.extend(mount_points.iter().map(String::as_str));
+        let mut context = BTreeMap::new();
+        context.insert(DISK_CONTEXT_KEY.to_string(), String::new());
+        run_command(&command, context);
+    }
+
+    pub fn handle_result(&mut self, exit_code: Option<i32>, stdout: &[u8], stderr: &[u8]) {
+        self.is_running = false;
+        // df fails when one of the mount points doesn't exist, but still prints the other ones
+        if exit_code != Some(0) {
+            eprintln!(
+                "Failed to run df: {}",
+                String::from_utf8_lossy(stderr).trim()
+            );
+        }
+        self.usage = parse_df(&String::from_utf8_lossy(stdout));
+    }
+
+    pub fn render(
+        &self,
+        thresholds: Thresholds,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        if self.usage.is_empty() {
+            return None;
+        }
+        let fg = palette.text_unselected.background;
+        let separator = format!("{} ", separator);
+        let mut part = style!(fg, palette.ribbon_unselected.background)
+            .paint(&separator)
+            .to_string();
+        let mut len = separator.width();
+        // every mount point gets the color of its own usage
+        for usage in &self.usage {
+            let text = format!("{} {:.0}% ", usage.mount_point, usage.percent);
+            len += text.width();
+            let bg = thresholds.color(usage.percent, palette);
+            part.push_str(&style!(fg, bg).bold().paint(text).to_string());
+        }
+
+        Some(LinePart {
+            part,
+            len,
+            tab_index: None,
+            segment: None,
+        })
+    }
+}
+
+// `Filesystem 1024-blocks Used Available Capacity Mounted on` then one line per file system,
+// mount points can contain spaces
+fn parse_df(df: &str) -> Vec<MountUsage> {
+    df.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let percent = fields.get(4)?.strip_suffix('%')?.parse().ok()?;
+            let mount_point = fields.get(5..).filter(|f| !f.is_empty())?.join(" ");
+            Some(MountUsage {
+                mount_point,
+                percent,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn df() {
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                  /dev/nvme0n1p2   490617784 301234567 164383217      65% /\n\
+                  /dev/sdb1        976285652 927471369  48814283      95% /mnt/backup disk\n\
+                  df: /mnt/gone: No such file or directory\n";
+        let usage = parse_df(df);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            (usage[0].mount_point.as_str(), usage[0].percent),
+            ("/", 65.0)
+        );
+        assert_eq!(
+            (usage[1].mount_point.as_str(), usage[1].percent),
+            ("/mnt/backup disk", 95.0)
+        );
+    }
+}
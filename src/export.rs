@@ -0,0 +1,59 @@
+use serde_json::{json, Value};
+use zellij_tile::prelude::*;
+
+use crate::line::strip_ansi;
+use crate::LinePart;
+
+// the state of the bar for other tools, tabs that didn't fit are marked as not visible
+pub fn bar_state(
+    tabs: &[TabInfo],
+    mode_info: &ModeInfo,
+    tab_line: &[LinePart],
+    previous_tab: Option<usize>,
+) -> Value {
+    let visible_tabs: Vec<usize> = tab_line
+        .iter()
+        .filter(|p| p.segment.is_none())
+        .filter_map(|p| p.tab_index)
+        .collect();
+    let active_tab = tabs.iter().find(|t| t.active);
+    let swap_layout = active_tab.and_then(|t| {
+        t.active_swap_layout_name.as_ref().map(|name| {
+            json!({
+                "name": name,
+                "dirty": t.is_swap_layout_dirty,
+            })
+        })
+    });
+    let tabs: Vec<Value> = tabs
+        .iter()
+        .map(|t| {
+            json!({
+                "position": t.position,
+                "name": t.name,
+                "active": t.active,
+                "visible": visible_tabs.contains(&t.position),
+                "fullscreen": t.is_fullscreen_active,
+                "sync_panes": t.is_sync_panes_active,
+            })
+        })
+        .collect();
+    json!({
+        "session": mode_info.session_name,
+        "mode": format!("{:?}", mode_info.mode).to_lowercase(),
+        "active_tab": active_tab.map(|t| t.position),
+        "previous_tab": previous_tab,
+        "swap_layout": swap_layout,
+        "tabs": tabs,
+    })
+}
+
+// the bar as it was last rendered, without colors
+pub fn bar_text(tab_line: &[LinePart]) -> String {
+    tab_line
+        .iter()
+        .map(|p| strip_ansi(&p.part))
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
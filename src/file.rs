@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use zellij_tile::prelude::*;
+
+use crate::command::truncate;
+use crate::config::FileConfig;
+use crate::line::strip_ansi;
+use crate::system::{host_path, render};
+use crate::LinePart;
+
+#[derive(Debug)]
+pub struct FileSegment {
+    config: FileConfig,
+    // none when the configured regex is invalid
+    regex: Option<Regex>,
+    // none when the file doesn't exist or nothing was extracted from it
+    value: Option<String>,
+}
+
+impl FileSegment {
+    pub fn new(config: FileConfig) -> Self {
+        // ^ and $ match at every line
+        let regex = config
+            .regex
+            .as_ref()
+            .map(|r| Regex::new(&format!("(?m){}", r)));
+        let regex = match regex {
+            Some(Ok(regex)) => Some(regex),
+            Some(Err(e)) => {
+                eprintln!("Invalid regex for file_{}: {}", config.name, e);
+                None
+            }
+            None => None,
+        };
+        FileSegment {
+            config,
+            regex,
+            value: None,
+        }
+    }
+
+    // the segment id used to hide it
+    pub fn id(&self) -> String {
+        format!("file_{}", self.config.name)
+    }
+
+    pub fn is_changed_by(&self, changed_paths: &[PathBuf]) -> bool {
+        is_changed(&self.config.path, changed_paths)
+    }
+
+    // returns whether the value changed
+    pub fn read(&mut self, host_folder: &Path) -> bool {
+        let value = fs::read_to_string(host_path(host_folder, &self.config.path))
+            .ok()
+            .and_then(|contents| self.extract(&contents))
+            .map(|value| self.config.template.replace("{value}", &value));
+        let changed = self.value != value;
+        self.value = value;
+        changed
+    }
+
+    // the first capture group of the regex, or the whole match without one, the configured line,
+    // or the first line that isn't empty
+    fn extract(&self, contents: &str) -> Option<String> {
+        let value = if self.config.regex.is_some() {
+            let captures = self.regex.as_ref()?.captures(contents)?;
+            captures.get(1).or_else(|| captures.get(0))?.as_str()
+        } else if let Some(line) = self.config.line {
+            contents.lines().nth(line.checked_sub(1)?)?
+        } else {
+            contents.lines().find(|l| !l.trim().is_empty())?
+        };
+        // escape sequences and control characters would break the bar and the length of the value
+        let value = strip_ansi(value);
+        Some(truncate(value.trim(), self.config.max_width)).filter(|v| !v.is_empty())
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let value = self.value.as_ref()?;
+        Some(render(
+            value,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+}
+
+// zellij reports changes with paths in the plugin filesystem, which start with /host and aren't
+// always relative to the same folder, so any path ending like the file's is a match
+pub fn is_changed(path: &Path, changed_paths: &[PathBuf]) -> bool {
+    changed_paths.iter().any(|changed| {
+        let changed = changed.strip_prefix("/host").unwrap_or(changed);
+        let changed = changed.strip_prefix("/").unwrap_or(changed);
+        !changed.as_os_str().is_empty() && path.ends_with(changed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(regex: Option<&str>, line: Option<usize>) -> FileSegment {
+        FileSegment::new(FileConfig {
+            name: "status".to_string(),
+            path: PathBuf::from("/home/me/.status"),
+            regex: regex.map(String::from),
+            line,
+            template: "{value}".to_string(),
+            max_width: 10,
+        })
+    }
+
+    #[test]
+    fn extract() {
+        let contents = "\n  todo: 3\ndone: 12\n";
+        assert_eq!(
+            file(None, None).extract(contents),
+            Some("todo: 3".to_string())
+        );
+        assert_eq!(
+            file(None, Some(3)).extract(contents),
+            Some("done: 12".to_string())
+        );
+        assert_eq!(file(None, Some(0)).extract(contents), None);
+        assert_eq!(file(None, Some(1)).extract(contents), None);
+        let regex = file(Some(r"^done: (\d+)"), None);
+        assert_eq!(regex.extract(contents), Some("12".to_string()));
+        // without a capture group, the whole match
+        let regex = file(Some(r"\d+"), None);
+        assert_eq!(regex.extract(contents), Some("3".to_string()));
+        // an invalid regex never matches
+        assert_eq!(file(Some("("), None).extract(contents), None);
+    }
+
+    #[test]
+    fn values_are_sanitized() {
+        let contents = "\u{1b}[31mred\u{1b}[0m\tvalue that is too long\n";
+        assert_eq!(
+            file(None, None).extract(contents),
+            Some("redvalue …".to_string())
+        );
+    }
+
+    #[test]
+    fn changed_paths() {
+        let path = Path::new("/home/me/.status");
+        assert!(is_changed(path, &[PathBuf::from("/host/.status")]));
+        assert!(is_changed(path, &[PathBuf::from("/host/me/.status")]));
+        assert!(is_changed(path, &[PathBuf::from("/home/me/.status")]));
+        assert!(!is_changed(path, &[PathBuf::from("/host/other")]));
+        assert!(!is_changed(path, &[PathBuf::from("/host")]));
+        assert!(!is_changed(path, &[]));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zellij_tile::prelude::*;
+
+use crate::system::render;
+use crate::LinePart;
+
+// key of the run_command context of git status runs, holding the directory it ran in
+pub const GIT_CONTEXT_KEY: &str = "git_status";
+// git runs this long after the last cwd change, so going through directories quickly runs it once
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct GitStatus {
+    branch: String,
+    ahead: usize,
+    behind: usize,
+    is_dirty: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GitSegment {
+    cwd: Option<PathBuf>,
+    cwd_changed_at: Option<Instant>,
+    // none outside of repositories
+    status: Option<GitStatus>,
+    // git is never started again before its last run is done
+    is_running: bool,
+    last_run_at: Option<Instant>,
+}
+
+impl GitSegment {
+    pub fn set_cwd(&mut self, cwd: &Path) {
+        if self.cwd.as_deref() != Some(cwd) {
+            self.cwd = Some(cwd.to_path_buf());
+            self.cwd_changed_at = Some(Instant::now());
+        }
+    }
+
+    // runs git if the cwd changed a moment ago or the status is older than interval
+    pub fn tick(&mut self, now: Instant, interval: Duration) {
+        let Some(cwd) = &self.cwd else {
+            return;
+        };
+        let is_due = match (self.cwd_changed_at, self.last_run_at) {
+            (Some(changed_at), _) => now.duration_since(changed_at) >= DEBOUNCE,
+            (None, Some(last_run_at)) => now.duration_since(last_run_at) >= interval,
+            (None, None) => true,
+        };
+        if self.is_running || !is_due {
+            return;
+        }
+        self.is_running = true;
+        self.last_run_at = Some(now);
+        self.cwd_changed_at = None;
+
+        let cwd = cwd.to_string_lossy().to_string();
+        let mut context = BTreeMap::new();
+        context.insert(GIT_CONTEXT_KEY.to_string(), cwd.clone());
+        run_command(
+            &[
+                "git",
+                "-C",
+                &cwd,
+                "--no-optional-locks",
+                "status",
+                "--porcelain=v2",
+                "--branch",
+            ],
+            context,
+        );
+    }
+
+    // returns whether the status changed
+    pub fn handle_result(&mut self, cwd: &str, exit_code: Option<i32>, stdout: &[u8]) -> bool {
+        self.is_running = false;
+        // the result of a directory that isn't focused anymore, the new one runs on the next tick
+        if self.cwd.as_deref() != Some(Path::new(cwd)) {
+            return false;
+        }
+        let status = match exit_code {
+            Some(0) => Some(parse_status(&String::from_utf8_lossy(stdout))),
+            // not a repository
+            _ => None,
+        };
+        let changed = self.status != status;
+        self.status = status;
+        changed
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let status = self.status.as_ref()?;
+        let mut text = status.branch.clone();
+        if status.ahead > 0 {
+            text.push_str(&format!(" ↑{}", status.ahead));
+        }
+        if status.behind > 0 {
+            text.push_str(&format!(" ↓{}", status.behind));
+        }
+        if status.is_dirty {
+            text.push_str(" *");
+        }
+        let bg = if status.is_dirty {
+            palette.text_unselected.emphasis_0
+        } else {
+            palette.ribbon_unselected.background
+        };
+        Some(render(&text, bg, palette, separator))
+    }
+}
+
+// `git status --porcelain=v2 --branch` output, header lines start with `#` and every other line
+// is a changed or untracked file
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut oid = None;
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("# ") {
+            let mut words = header.split_whitespace();
+            match words.next() {
+                Some("branch.oid") => oid = words.next(),
+                Some("branch.head") => status.branch = words.next().unwrap_or("").to_string(),
+                Some("branch.ab") => {
+                    let mut count = |prefix| {
+                        words
+                            .next()
+                            .and_then(|w| w.strip_prefix(prefix))
+                            .and_then(|n| n.parse().ok())
+                            .unwrap_or(0)
+                    };
+                    status.ahead = count('+');
+                    status.behind = count('-');
+                }
+                _ => {}
+            }
+        } else if !line.is_empty() {
+            status.is_dirty = true;
+        }
+    }
+    // the short commit hash instead of `(detached)`
+    if status.branch == "(detached)" {
+        if let Some(oid) = oid.filter(|o| *o != "(initial)") {
+            status.branch = oid.chars().take(7).collect();
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_with_ahead_and_behind() {
+        let status = parse_status(
+            "# branch.oid 1234567890abcdef\n# branch.head main\n# branch.upstream origin/main\n\
+             # branch.ab +2 -1\n",
+        );
+        assert_eq!(status.branch, "main");
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert!(!status.is_dirty);
+    }
+
+    #[test]
+    fn changed_and_untracked_files_are_dirty() {
+        let status = parse_status("# branch.head main\n? notes.txt\n");
+        assert!(status.is_dirty);
+        let status = parse_status("# branch.head main\n1 .M N... 100644 100644 100644 a b f.rs\n");
+        assert!(status.is_dirty);
+    }
+
+    #[test]
+    fn detached_head_shows_the_commit() {
+        let status = parse_status("# branch.oid 1234567890abcdef\n# branch.head (detached)\n");
+        assert_eq!(status.branch, "1234567");
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::file::is_changed;
+use crate::line::strip_ansi;
+use crate::meeting::all_day_events;
+use crate::system::host_path;
+
+// recurring holidays in icalendar files are expanded this far around the day they're read
+const EXPAND_DAYS: i64 = 3 * 366;
+
+#[derive(Debug, Default)]
+pub struct Holidays {
+    // the holiday files, with their region
+    files: Vec<(String, PathBuf)>,
+    // upcoming holidays are described this many days ahead
+    within_days: i64,
+    // the names of the holidays of each day, with their region
+    days: BTreeMap<NaiveDate, Vec<(String, String)>>,
+}
+
+impl Holidays {
+    pub fn new(files: Vec<(String, PathBuf)>, within_days: i64) -> Self {
+        Holidays {
+            files,
+            within_days,
+            days: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_changed_by(&self, changed_paths: &[PathBuf]) -> bool {
+        self.files
+            .iter()
+            .any(|(_, path)| is_changed(path, changed_paths))
+    }
+
+    pub fn read(&mut self, host_folder: &Path, today: NaiveDate) {
+        self.days.clear();
+        for (region, path) in self.files.clone() {
+            match fs::read_to_string(host_path(host_folder, &path)) {
+                Ok(contents) => self.add(&region, &contents, today),
+                Err(e) => eprintln!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    // the holidays of a file of region
+    pub fn add(&mut self, region: &str, contents: &str, today: NaiveDate) {
+        for (date, name) in parse_file(contents, today) {
+            self.days
+                .entry(date)
+                .or_default()
+                .push((region.to_string(), name));
+        }
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.days.contains_key(&date)
+    }
+
+    // the business days after today, up to and including the date
+    pub fn business_days_until(&self, today: NaiveDate, date: NaiveDate) -> usize {
+        today
+            .iter_days()
+            .skip(1)
+            .take_while(|d| *d <= date)
+            .filter(|d| self.is_business_day(*d))
+            .count()
+    }
+
+    // the holidays of today, or the next ones with their date, eg. `成人の日 1/12`
+    pub fn describe(&self, today: NaiveDate) -> Option<String> {
+        // the range ends with the dates when within_days goes past them
+        let last_day = Duration::try_days(self.within_days)
+            .and_then(|days| today.checked_add_signed(days))
+            .unwrap_or(NaiveDate::MAX);
+        let (date, holidays) = self.days.range(today..=last_day).next()?;
+        // the region tells them apart once there are several
+        let names: Vec<String> = holidays
+            .iter()
+            .map(|(region, name)| {
+                if self.files.len() > 1 {
+                    format!("{} ({})", name, region)
+                } else {
+                    name.clone()
+                }
+            })
+            .collect();
+        let names = names.join(" / ");
+        if *date == today {
+            Some(names)
+        } else {
+            Some(format!("{} {}/{}", names, date.month(), date.day()))
+        }
+    }
+}
+
+// icalendar files, or `YYYY-MM-DD name` lines
+fn parse_file(contents: &str, today: NaiveDate) -> Vec<(NaiveDate, String)> {
+    if contents.trim_start().starts_with("BEGIN:VCALENDAR") {
+        all_day_events(
+            contents,
+            today - Duration::days(EXPAND_DAYS),
+            today + Duration::days(EXPAND_DAYS),
+        )
+    } else {
+        parse_holidays(contents)
+    }
+}
+
+// `2026-01-01 元日`, empty lines and lines starting with # are skipped
+fn parse_holidays(contents: &str) -> Vec<(NaiveDate, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (date, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => Some((date, strip_ansi(name).trim().to_string())),
+                Err(_) => {
+                    eprintln!("Invalid holiday: {}", line);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\n\
+                            BEGIN:VEVENT\n\
+                            SUMMARY:成人の日\n\
+                            DTSTART;VALUE=DATE:20260112\n\
+                            RRULE:FREQ=YEARLY;BYMONTH=1;BYDAY=2MO\n\
+                            END:VEVENT\n\
+                            END:VCALENDAR\n";
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn holidays(contents: &str, today: NaiveDate) -> Holidays {
+        let mut holidays = Holidays::new(vec![("jp".to_string(), PathBuf::new())], 30);
+        holidays.add("jp", contents, today);
+        holidays
+    }
+
+    #[test]
+    fn yearly_weekday_holidays() {
+        let holidays = holidays(CALENDAR, date(2027, 1, 1));
+        assert_eq!(
+            holidays.describe(date(2027, 1, 1)),
+            Some("成人の日 1/11".to_string())
+        );
+        assert_eq!(
+            holidays.describe(date(2028, 1, 10)),
+            Some("成人の日".to_string())
+        );
+        // only the second monday of january
+        assert!(holidays.is_business_day(date(2027, 1, 4)));
+        assert!(!holidays.is_business_day(date(2027, 1, 11)));
+        assert!(holidays.is_business_day(date(2027, 2, 8)));
+    }
+
+    #[test]
+    fn business_days() {
+        let holidays = holidays(
+            "# new year\n2027-01-01 元日\n\n2027-01-04 休み\n",
+            date(2026, 12, 1),
+        );
+        // from thursday, friday and monday are holidays
+        assert_eq!(
+            holidays.business_days_until(date(2026, 12, 31), date(2027, 1, 5)),
+            1
+        );
+        assert_eq!(
+            holidays.describe(date(2027, 1, 2)),
+            Some("休み 1/4".to_string())
+        );
+    }
+
+    #[test]
+    fn names_are_sanitized() {
+        let holidays = holidays("2027-01-01 \u{1b}[31m元日\u{7}\n", date(2026, 12, 1));
+        assert_eq!(
+            holidays.describe(date(2027, 1, 1)),
+            Some("元日".to_string())
+        );
+    }
+
+    #[test]
+    fn huge_ranges_end_with_the_dates() {
+        let mut holidays = holidays("2027-01-04 休み\n", date(2026, 12, 1));
+        holidays.within_days = i64::MAX;
+        assert_eq!(
+            holidays.describe(date(2027, 1, 2)),
+            Some("休み 1/4".to_string())
+        );
+        holidays.within_days = 30;
+        assert_eq!(holidays.describe(NaiveDate::MAX), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use zellij_tile::prelude::*;
+
+use crate::file::is_changed;
+use crate::system::{host_path, render};
+use crate::LinePart;
+
+// key of the run_command context of the command printing KUBECONFIG and HOME
+pub const KUBE_CONTEXT_KEY: &str = "kube_config";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KubeContext {
+    name: String,
+    namespace: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Kube {
+    // contexts matching it are shown in red
+    production: Option<Regex>,
+    // the kubeconfig files, in the order kubectl merges them, none until they're known. The
+    // configured kubeconfig, or the files in KUBECONFIG
+    paths: Option<Vec<PathBuf>>,
+    is_finding_paths: bool,
+    context: Option<KubeContext>,
+}
+
+impl Kube {
+    pub fn new(config_path: Option<PathBuf>, production: Option<&str>) -> Self {
+        let production = production.and_then(|p| match Regex::new(p) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                eprintln!("Invalid regex for kube_production: {}", e);
+                None
+            }
+        });
+        Kube {
+            paths: config_path.map(|path| vec![path]),
+            production,
+            ..Default::default()
+        }
+    }
+
+    // the kubeconfig files come from the environment of zellij when they aren't configured
+    pub fn find_paths(&mut self) {
+        if self.paths.is_some() || self.is_finding_paths {
+            return;
+        }
+        self.is_finding_paths = true;
+        let mut context = BTreeMap::new();
+        context.insert(KUBE_CONTEXT_KEY.to_string(), String::new());
+        run_command(
+            &["sh", "-c", "printf '%s\\n%s\\n' \"$KUBECONFIG\" \"$HOME\""],
+            context,
+        );
+    }
+
+    // the kubeconfig files are read once the host folder contains them
+    pub fn handle_result(&mut self, exit_code: Option<i32>, stdout: &[u8]) {
+        self.is_finding_paths = false;
+        if exit_code != Some(0) {
+            eprintln!("Failed to find the kubeconfig");
+            return;
+        }
+        let stdout = String::from_utf8_lossy(stdout);
+        let mut lines = stdout.lines();
+        let kubeconfig = lines.next().unwrap_or("");
+        let home = lines.next().unwrap_or("");
+        let paths: Vec<PathBuf> = kubeconfig
+            .split(':')
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        self.paths = Some(if !paths.is_empty() {
+            paths
+        } else {
+            vec![Path::new(home).join(".kube/config")]
+        });
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().flatten().map(PathBuf::as_path)
+    }
+
+    pub fn is_changed_by(&self, changed_paths: &[PathBuf]) -> bool {
+        self.paths
+            .iter()
+            .flatten()
+            .any(|path| is_changed(path, changed_paths))
+    }
+
+    // returns whether the context changed
+    pub fn read(&mut self, host_folder: &Path) -> bool {
+        let Some(paths) = &self.paths else {
+            return false;
+        };
+        let configs: Vec<KubeConfig> = paths
+            .iter()
+            .filter_map(|path| fs::read_to_string(host_path(host_folder, path)).ok())
+            .map(|config| parse_kubeconfig(&config))
+            .collect();
+        // like kubectl, the first file setting the current context or defining a context wins
+        let context = configs
+            .iter()
+            .find_map(|c| c.current_context.clone())
+            .map(|name| {
+                let namespace = configs
+                    .iter()
+                    .find_map(|c| c.namespaces.get(&name).cloned())
+                    .flatten()
+                    .unwrap_or_else(|| "default".to_string());
+                KubeContext { name, namespace }
+            });
+        let changed = self.context != context;
+        self.context = context;
+        changed
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let context = self.context.as_ref()?;
+        let is_production = self
+            .production
+            .as_ref()
+            .is_some_and(|p| p.is_match(&context.name));
+        let text = if is_production {
+            format!("⎈ {}/{} !", context.name.to_uppercase(), context.namespace)
+        } else {
+            format!("⎈ {}/{}", context.name, context.namespace)
+        };
+        let bg = if is_production {
+            palette.exit_code_error.base
+        } else {
+            palette.ribbon_unselected.background
+        };
+        Some(render(&text, bg, palette, separator))
+    }
+}
+
+#[derive(Debug, Default)]
+struct KubeConfig {
+    current_context: Option<String>,
+    // namespaces by context name, none when the context doesn't set one
+    namespaces: BTreeMap<String, Option<String>>,
+}
+
+// only the parts of the yaml that are needed, in the block style kubectl writes:
+//
+// contexts:
+// - context:
+//     cluster: dev
+//     namespace: web
+//   name: dev
+// current-context: dev
+fn parse_kubeconfig(config: &str) -> KubeConfig {
+    let mut kube_config = KubeConfig::default();
+    let mut in_contexts = false;
+    // the indentation of the keys of the current list item, with its name and namespace
+    let mut item: Option<(usize, Option<String>, Option<String>)> = None;
+    let mut end_item = |item: &mut Option<(usize, Option<String>, Option<String>)>| {
+        if let Some((_, Some(name), namespace)) = item.take() {
+            kube_config.namespaces.entry(name).or_insert(namespace);
+        }
+    };
+    let mut current_context = None;
+    for line in config.lines() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        // top level keys
+        if indent == 0 && !content.starts_with('-') {
+            end_item(&mut item);
+            in_contexts = content.trim_end() == "contexts:";
+            if let Some(value) = content.strip_prefix("current-context:") {
+                current_context = Some(unquote(value)).filter(|v| !v.is_empty());
+            }
+            continue;
+        }
+        if !in_contexts {
+            continue;
+        }
+        // a new list item, its keys are indented past the dash
+        let (indent, content) = match content.strip_prefix('-') {
+            Some(rest) => {
+                end_item(&mut item);
+                let key = rest.trim_start();
+                let key_indent = indent + 1 + (rest.len() - key.len());
+                item = Some((key_indent, None, None));
+                (key_indent, key)
+            }
+            None => (indent, content),
+        };
+        let Some((item_indent, name, namespace)) = item.as_mut() else {
+            continue;
+        };
+        if let Some(value) = content.strip_prefix("name:") {
+            if indent == *item_indent {
+                *name = Some(unquote(value));
+            }
+        } else if let Some(value) = content.strip_prefix("namespace:") {
+            if indent > *item_indent {
+                *namespace = Some(unquote(value)).filter(|v| !v.is_empty());
+            }
+        }
+    }
+    end_item(&mut item);
+    kube_config.current_context = current_context;
+    kube_config
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_style() {
+        let config = "apiVersion: v1\n\
+                      clusters:\n\
+                      - cluster:\n    \
+                          server: https://dev.example.com\n  \
+                        name: dev\n\
+                      contexts:\n\
+                      - context:\n    \
+                          cluster: dev\n    \
+                          namespace: web\n  \
+                        name: dev\n\
+                      - context:\n    \
+                          cluster: prod\n  \
+                        name: prod\n\
+                      current-context: dev\n\
+                      kind: Config\n";
+        let config = parse_kubeconfig(config);
+        assert_eq!(config.current_context.as_deref(), Some("dev"));
+        assert_eq!(config.namespaces.get("dev"), Some(&Some("web".to_string())));
+        assert_eq!(config.namespaces.get("prod"), Some(&None));
+        assert_eq!(config.namespaces.len(), 2);
+    }
+
+    #[test]
+    fn name_first_and_quoted_values() {
+        let config = "contexts:\n  \
+                        - name: \"staging\"\n    \
+                          context:\n      \
+                            cluster: staging\n      \
+                            namespace: 'api'\n\
+                      current-context: 'staging'\n";
+        let config = parse_kubeconfig(config);
+        assert_eq!(config.current_context.as_deref(), Some("staging"));
+        assert_eq!(
+            config.namespaces.get("staging"),
+            Some(&Some("api".to_string()))
+        );
+    }
+
+    #[test]
+    fn namespaces_outside_contexts_are_ignored() {
+        let config = "contexts:\n\
+                      - context:\n    \
+                          cluster: dev\n  \
+                        name: dev\n\
+                      users:\n\
+                      - name: dev\n  \
+                        user:\n    \
+                          namespace: other\n\
+                      current-context: dev\n";
+        let config = parse_kubeconfig(config);
+        assert_eq!(config.namespaces.get("dev"), Some(&None));
+        assert_eq!(config.namespaces.len(), 1);
+    }
+}
@@ -1,13 +1,22 @@
 use ansi_term::ANSIStrings;
 use chrono::Local;
-use chrono::Locale;
 use chrono_tz::Tz;
 use unicode_width::UnicodeWidthStr;
 
-use crate::{LinePart, ARROW_SEPARATOR};
+use crate::config::Config;
+use crate::date::format_date;
+use crate::holiday::Holidays;
+use crate::{LinePart, Segment, ARROW_SEPARATOR};
 use zellij_tile::prelude::*;
 use zellij_tile_utils::style;
 
+// the local timezone isn't available to plugins
+pub const TIMEZONE: Tz = Tz::Asia__Hong_Kong;
+
+// the forms a segment can be shown in, from the widest, eg. all the timers or only the soonest one,
+// each made of one or more parts
+pub type SegmentForms = Vec<Vec<LinePart>>;
+
 fn get_current_title_len(current_title: &[LinePart]) -> usize {
     current_title.iter().map(|p| p.len).sum()
 }
@@ -139,6 +148,7 @@ fn left_more_message(
         part: more_styled_text,
         len: more_text_len,
         tab_index: Some(tab_index),
+        segment: Some(Segment::MoreTabs),
     }
 }
 
@@ -173,12 +183,13 @@ fn right_more_message(
         part: more_styled_text,
         len: more_text_len,
         tab_index: Some(tab_index),
+        segment: Some(Segment::MoreTabs),
     }
 }
 
 fn tab_line_prefix(
     session_name: Option<&str>,
-    mode: InputMode,
+    mode: Option<InputMode>,
     palette: Styling,
     cols: usize,
 ) -> Vec<LinePart> {
@@ -196,6 +207,7 @@ fn tab_line_prefix(
         part: prefix_styled_text.to_string(),
         len: prefix_text_len,
         tab_index: None,
+        segment: None,
     }];
     if let Some(name) = session_name {
         let name_part = format!("({})", name);
@@ -206,9 +218,14 @@ fn tab_line_prefix(
                 part: name_part_styled_text.to_string(),
                 len: name_part_len,
                 tab_index: None,
+                segment: None,
             })
         }
     }
+    let mode = match mode {
+        Some(mode) => mode,
+        None => return parts,
+    };
     let mode_part = format!("{:?}", mode).to_uppercase();
     let mode_part_padded = format!(" {} ", mode_part);
     let mode_part_len = mode_part_padded.width();
@@ -230,11 +247,72 @@ fn tab_line_prefix(
             part: format!("{}", mode_part_styled_text),
             len: mode_part_len,
             tab_index: None,
+            segment: Some(Segment::Mode),
         })
     }
     parts
 }
 
+fn new_tab_button(palette: Styling, separator: &str) -> LinePart {
+    let text = " + ";
+    let len = text.width() + 2 * separator.width();
+    let (text_color, sep_color) = (
+        palette.ribbon_unselected.base,
+        palette.text_unselected.background,
+    );
+    let button_bg = palette.text_selected.emphasis_0;
+    let left_separator = style!(sep_color, button_bg).paint(separator);
+    let styled_text = style!(text_color, button_bg).bold().paint(text);
+    let right_separator = style!(button_bg, sep_color).paint(separator);
+    LinePart {
+        part: ANSIStrings(&[left_separator, styled_text, right_separator]).to_string(),
+        len,
+        tab_index: None,
+        segment: Some(Segment::NewTab),
+    }
+}
+
+// the modes that can be entered from normal mode, the rest are reached from within these
+const PICKABLE_MODES: [InputMode; 8] = [
+    InputMode::Normal,
+    InputMode::Locked,
+    InputMode::Pane,
+    InputMode::Tab,
+    InputMode::Resize,
+    InputMode::Move,
+    InputMode::Scroll,
+    InputMode::Session,
+];
+
+// shown instead of the tabs while the mode picker is open
+fn mode_picker(mode: InputMode, palette: Styling, cols: usize) -> Vec<LinePart> {
+    let text_color = palette.ribbon_unselected.base;
+    let bg_color = palette.text_unselected.background;
+    let choice_color = palette.ribbon_unselected.background;
+
+    let mut parts = vec![];
+    let mut len = 0;
+    for choice in PICKABLE_MODES.iter().filter(|m| **m != mode) {
+        let choice_text = format!(" {:?} ", choice).to_uppercase();
+        let choice_len = choice_text.width() + 1; // 1 for the space between choices
+        if len + choice_len > cols {
+            break;
+        }
+        len += choice_len;
+        let choice_styled_text = [
+            style!(bg_color, bg_color).paint(" "),
+            style!(text_color, choice_color).bold().paint(choice_text),
+        ];
+        parts.push(LinePart {
+            part: ANSIStrings(&choice_styled_text).to_string(),
+            len: choice_len,
+            tab_index: None,
+            segment: Some(Segment::ModeChoice(*choice)),
+        });
+    }
+    parts
+}
+
 pub fn tab_separator(capabilities: PluginCapabilities) -> &'static str {
     if !capabilities.arrow_fonts {
         ARROW_SEPARATOR
@@ -243,18 +321,42 @@ pub fn tab_separator(capabilities: PluginCapabilities) -> &'static str {
     }
 }
 
+// what the tab line is drawn from, besides the tabs and the segments
+pub struct LineState<'a> {
+    pub session_name: Option<&'a str>,
+    pub cols: usize,
+    pub palette: Styling,
+    pub capabilities: PluginCapabilities,
+    pub hide_session_name: bool,
+    pub mode: InputMode,
+    pub active_swap_layout_name: &'a Option<String>,
+    pub is_swap_layout_dirty: bool,
+    pub mode_picker_open: bool,
+    pub is_time_alt: bool,
+    pub holidays: &'a Holidays,
+    pub config: &'a Config,
+}
+
 pub fn tab_line(
-    session_name: Option<&str>,
+    state: LineState,
     mut all_tabs: Vec<LinePart>,
     active_tab_index: usize,
-    cols: usize,
-    palette: Styling,
-    capabilities: PluginCapabilities,
-    hide_session_name: bool,
-    mode: InputMode,
-    active_swap_layout_name: &Option<String>,
-    is_swap_layout_dirty: bool,
+    segments: Vec<SegmentForms>,
 ) -> Vec<LinePart> {
+    let LineState {
+        session_name,
+        cols,
+        palette,
+        capabilities,
+        hide_session_name,
+        mode,
+        active_swap_layout_name,
+        is_swap_layout_dirty,
+        mode_picker_open,
+        is_time_alt,
+        holidays,
+        config,
+    } = state;
     let mut tabs_after_active = all_tabs.split_off(active_tab_index);
     let mut tabs_before_active = all_tabs;
     let active_tab = if !tabs_after_active.is_empty() {
@@ -262,45 +364,81 @@ pub fn tab_line(
     } else {
         tabs_before_active.pop().unwrap()
     };
-    let mut prefix = match hide_session_name {
-        true => tab_line_prefix(None, mode, palette, cols),
-        false => tab_line_prefix(session_name, mode, palette, cols),
-    };
+    let session_name = session_name.filter(|_| !hide_session_name && config.shows("session"));
+    let shown_mode = Some(mode).filter(|_| config.shows("mode"));
+    let mut prefix = tab_line_prefix(session_name, shown_mode, palette, cols);
     let prefix_len = get_current_title_len(&prefix);
 
+    if mode_picker_open {
+        prefix.append(&mut mode_picker(
+            mode,
+            palette,
+            cols.saturating_sub(prefix_len),
+        ));
+        return prefix;
+    }
+
     // if active tab alone won't fit in cols, don't draw any tabs
     if prefix_len + active_tab.len > cols {
         return prefix;
     }
 
+    let active_tab_len = active_tab.len;
     let mut tabs_to_render = vec![active_tab];
 
+    // the button is only shown if it fits next to the active tab, tabs are collapsed to make room
+    let button = new_tab_button(palette, tab_separator(capabilities));
+    let show_new_tab_button = config.new_tab_button
+        && config.shows("new_tab")
+        && prefix_len + active_tab_len + button.len <= cols;
+    let button_len = if show_new_tab_button { button.len } else { 0 };
+
     populate_tabs_in_tab_line(
         &mut tabs_before_active,
         &mut tabs_after_active,
         &mut tabs_to_render,
-        cols.saturating_sub(prefix_len),
+        cols.saturating_sub(prefix_len + button_len),
         palette,
         capabilities,
     );
     prefix.append(&mut tabs_to_render);
+    if show_new_tab_button {
+        prefix.push(button);
+    }
 
     let current_title_len = get_current_title_len(&prefix);
 
     let mut right_parts = vec![];
     let mut remaining_space = cols - current_title_len;
     let separator = tab_separator(capabilities);
-    let time_status = time_status(palette, &separator);
-    if remaining_space >= time_status.len {
+    let time_format = match &config.time_alt_format {
+        Some(format) if is_time_alt => format,
+        _ => &config.time_format,
+    };
+    let time_status = time_status(time_format, holidays, palette, &separator);
+    if config.shows("time") && remaining_space >= time_status.len {
         remaining_space -= time_status.len;
         right_parts.push(time_status);
     }
 
-    if remaining_space > 0 {
+    // the other segments, from the most to the least important, are shown while they fit, in the
+    // first of their forms that does
+    for forms in segments {
+        let form = forms
+            .into_iter()
+            .find(|parts| remaining_space >= get_current_title_len(parts));
+        if let Some(parts) = form {
+            remaining_space -= get_current_title_len(&parts);
+            right_parts.extend(parts);
+        }
+    }
+
+    if remaining_space > 0 && config.shows("swap_layout") {
         if let Some(swap_layout_status) = swap_layout_status(
             remaining_space,
             active_swap_layout_name,
             is_swap_layout_dirty,
+            &config.swap_layouts,
             mode,
             &palette,
             &separator,
@@ -320,6 +458,7 @@ pub fn tab_line(
         part: buffer,
         len: remaining_space,
         tab_index: None,
+        segment: None,
     });
     right_parts.reverse();
     for part in right_parts {
@@ -333,13 +472,25 @@ fn swap_layout_status(
     max_len: usize,
     swap_layout_name: &Option<String>,
     is_swap_layout_damaged: bool,
+    swap_layouts: &[String],
     input_mode: InputMode,
     palette: &Styling,
     separator: &str,
 ) -> Option<LinePart> {
     match swap_layout_name {
         Some(swap_layout_name) => {
-            let mut swap_layout_name = format!(" {} ", swap_layout_name);
+            let position = swap_layouts
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(swap_layout_name));
+            let mut swap_layout_name = match position {
+                Some(position) => format!(
+                    " {} {}/{} ",
+                    swap_layout_name,
+                    position + 1,
+                    swap_layouts.len()
+                ),
+                None => format!(" {} ", swap_layout_name),
+            };
             swap_layout_name.make_ascii_uppercase();
             let bg = palette.text_unselected.background;
             let fg = palette.ribbon_unselected.background;
@@ -372,17 +523,22 @@ fn swap_layout_status(
             let swap_layout_name_len = swap_layout_name.len() + separator.width() * 2;
             let (part, full_len) = (format!("{}", swap_layout_indicator), swap_layout_name_len);
             let short_len = swap_layout_name_len + 1; // 1 is the space between
+            let segment = Some(Segment::SwapLayout {
+                is_dirty: is_swap_layout_damaged,
+            });
             if full_len <= max_len {
                 Some(LinePart {
                     part,
                     len: full_len,
                     tab_index: None,
+                    segment,
                 })
             } else if short_len <= max_len && input_mode != InputMode::Locked {
                 Some(LinePart {
                     part: swap_layout_indicator,
                     len: short_len,
                     tab_index: None,
+                    segment,
                 })
             } else {
                 None
@@ -392,11 +548,8 @@ fn swap_layout_status(
     }
 }
 
-fn time_status(palette: Styling, separator: &str) -> LinePart {
-    let time = Local::now()
-        .with_timezone(&Tz::Asia__Hong_Kong)
-        .format_localized(" %H:%M:%S %A ", Locale::ja_JP)
-        .to_string();
+fn time_status(format: &str, holidays: &Holidays, palette: Styling, separator: &str) -> LinePart {
+    let time = format_date(Local::now().with_timezone(&TIMEZONE), format, holidays);
 
     let part = format!("{}{}", separator, time.to_string());
     let len = part.width();
@@ -409,6 +562,40 @@ fn time_status(palette: Styling, separator: &str) -> LinePart {
         part,
         len,
         tab_index: None,
+        segment: Some(Segment::Time),
     }
 }
 
+// removes escape sequences, leaving only the printable text
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            if !c.is_control() {
+                stripped.push(c);
+            }
+            continue;
+        }
+        match chars.next() {
+            // CSI, ends with a byte in the @ to ~ range
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, ends with BEL or ST
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    stripped
+}
@@ -1,21 +1,83 @@
+mod battery;
+mod command;
+mod config;
+mod cwd;
+mod date;
+mod disk;
+mod export;
+mod file;
+mod git;
+mod holiday;
+mod kube;
 mod line;
+mod meeting;
+mod mru;
+mod network;
+mod notification;
+mod pipe;
+mod pomodoro;
+mod progress;
+mod sun;
+mod system;
 mod tab;
+mod thermal;
+mod timer;
 
-use std::cmp::{max, min};
 use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use tab::get_tab_to_focus;
+use chrono::{NaiveDate, Utc};
+use tab::{get_clicked_line_part, get_clicked_tab, get_tab_to_focus, DropMarker, TabMarkers};
 use zellij_tile::prelude::*;
 
-use crate::line::tab_line;
+use crate::battery::{Battery, SUPPLY_FILES};
+use crate::command::{CommandSegment, COMMAND_CONTEXT_KEY};
+use crate::config::{Config, ScrollOrder, TimeClick};
+use crate::cwd::CwdTracker;
+use crate::disk::{DiskUsage, DISK_CONTEXT_KEY};
+use crate::export::{bar_state, bar_text};
+use crate::file::FileSegment;
+use crate::git::{GitSegment, GIT_CONTEXT_KEY};
+use crate::holiday::Holidays;
+use crate::kube::{Kube, KUBE_CONTEXT_KEY};
+use crate::line::{tab_line, tab_separator, LineState, SegmentForms, TIMEZONE};
+use crate::meeting::Meeting;
+use crate::mru::TabHistory;
+use crate::network::{Network, NET_DEV};
+use crate::notification::{Level, Notifications};
+use crate::pipe::{reply, PipeCommand};
+use crate::pomodoro::{Pomodoro, PomodoroAction};
+use crate::progress::Progress;
+use crate::system::{common_folder, SystemFiles, SystemStats, PROC_FILES, SYSTEM_CONTEXT_KEY};
 use crate::tab::tab_style;
+use crate::thermal::{Thermal, ZONE_FILES};
+use crate::timer::{TimerAction, TimerSelector, Timers};
 
 #[derive(Debug, Default)]
 pub struct LinePart {
     part: String,
     len: usize,
     tab_index: Option<usize>,
+    segment: Option<Segment>,
+}
+
+// clickable parts of the line that aren't tabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    SwapLayout { is_dirty: bool },
+    Mode,
+    ModeChoice(InputMode),
+    NewTab,
+    Notification(usize),
+    NotificationBadge,
+    Pomodoro,
+    // the clock, clicking it starts a timer
+    Time,
+    Timer(usize),
+    // the collapsed tabs indicators, they also have the tab_index of the next hidden tab
+    MoreTabs,
 }
 
 #[derive(Default)]
@@ -25,25 +87,82 @@ struct State {
     active_tab_idx: usize,
     mode_info: ModeInfo,
     tab_line: Vec<LinePart>,
+    // kept to apply changes made through pipes on top of it
+    configuration: BTreeMap<String, String>,
+    config: Config,
+    mode_picker_open: bool,
+    requested_permissions: Vec<PermissionType>,
+    // the alternate calendar is shown instead of the time after clicking the clock, or for a
+    // moment after hovering it, zellij doesn't tell when the mouse leaves
+    is_time_alt: bool,
+    time_hovered_at: Option<Instant>,
+    panes: PaneManifest,
+    // position of the tab that will be closed on the next right click on it
+    pending_close_tab: Option<usize>,
+    // positions of the tab being dragged and of the tab it would be dropped on
+    dragged_tab: Option<usize>,
+    drop_target: Option<usize>,
+    tab_history: TabHistory,
+    // the mru order is frozen while scrolling through it, otherwise every scroll would just go
+    // back to the previous tab
+    scroll_cycle: Vec<usize>,
+    scroll_target: Option<usize>,
+    notifications: Notifications,
+    progress: Progress,
+    commands: Vec<CommandSegment>,
+    files: Vec<FileSegment>,
+    kube: Kube,
+    meeting: Meeting,
+    holidays: Holidays,
+    pomodoro: Pomodoro,
+    timers: Timers,
+    cwd: CwdTracker,
+    git: GitSegment,
+    system_files: SystemFiles,
+    system: SystemStats,
+    battery: Battery,
+    network: Network,
+    disk: DiskUsage,
+    thermal: Thermal,
+    // the host folder zellij changed to, and the last one asked for
+    host_folder: Option<PathBuf>,
+    requested_host_folder: Option<PathBuf>,
 }
 
 static ARROW_SEPARATOR: &str = "";
+// how long the alternate calendar stays after the last hover on the clock
+const TIME_HOVER_FOR: Duration = Duration::from_secs(3);
+// key of the run_command context of the commands moving tabs
+const MOVE_TAB_CONTEXT_KEY: &str = "move_tab";
 
 register_plugin!(State);
 
+// the plugin api is imported from zellij, tests run without it
+#[cfg(test)]
+#[no_mangle]
+extern "C" fn host_run_plugin_command() {}
+
 impl ZellijPlugin for State {
-    fn load(&mut self, _configuration: BTreeMap<String, String>) {
-        request_permission(&[
-            PermissionType::ReadApplicationState,
-            PermissionType::ChangeApplicationState,
-        ]);
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.configuration = configuration;
+        self.apply_configuration();
+        self.notifications = Notifications::load(self.config.notification_history);
+        self.pomodoro = Pomodoro::load();
         set_selectable(false);
         subscribe(&[
             EventType::TabUpdate,
             EventType::ModeUpdate,
+            EventType::PaneUpdate,
             EventType::Mouse,
             EventType::PermissionRequestResult,
             EventType::Timer,
+            EventType::RunCommandResult,
+            EventType::CustomMessage,
+            EventType::HostFolderChanged,
+            EventType::FailedToChangeHostFolder,
+            EventType::FileSystemCreate,
+            EventType::FileSystemUpdate,
+            EventType::FileSystemDelete,
         ]);
         self.got_permissions = false;
         set_timeout(1.0);
@@ -55,6 +174,7 @@ impl ZellijPlugin for State {
             return match event {
                 Event::PermissionRequestResult(PermissionStatus::Granted) => {
                     self.got_permissions = true;
+                    self.update_host_folder();
                     true
                 }
                 _ => should_render,
@@ -62,12 +182,22 @@ impl ZellijPlugin for State {
         }
 
         match event {
+            // more permissions are asked for when the configuration changes
+            Event::PermissionRequestResult(PermissionStatus::Granted) => {
+                self.update_host_folder();
+                should_render = true;
+            }
             Event::ModeUpdate(mode_info) => {
                 if self.mode_info != mode_info {
                     should_render = true;
                 }
                 self.mode_info = mode_info
             }
+            Event::PaneUpdate(panes) => {
+                self.panes = panes;
+                self.cwd
+                    .update_panes(&self.panes, self.tabs.iter().find(|t| t.active));
+            }
             Event::TabUpdate(tabs) => {
                 if let Some(active_tab_index) = tabs.iter().position(|t| t.active) {
                     // tabs are indexed starting from 1 so we need to add 1
@@ -76,30 +206,170 @@ impl ZellijPlugin for State {
                         should_render = true;
                     }
                     self.active_tab_idx = active_tab_idx;
+                    let old_names: Vec<String> = self.tabs.iter().map(|t| t.name.clone()).collect();
+                    let new_names: Vec<String> = tabs.iter().map(|t| t.name.clone()).collect();
+                    self.tab_history.update_tabs(&old_names, &new_names);
+                    self.tab_history.focus(active_tab_index, tabs.len());
+                    if self.scroll_target != Some(active_tab_index) {
+                        self.scroll_cycle.clear();
+                        self.scroll_target = None;
+                    }
                     self.tabs = tabs;
+                    self.cwd
+                        .update_panes(&self.panes, self.tabs.iter().find(|t| t.active));
                 } else {
                     eprintln!("Could not find active tab.");
                 }
             }
             Event::Mouse(me) => match me {
                 Mouse::LeftClick(_, col) => {
-                    let tab_to_focus = get_tab_to_focus(&self.tab_line, self.active_tab_idx, col);
-                    if let Some(idx) = tab_to_focus {
-                        switch_tab_to(idx.try_into().unwrap());
+                    should_render = self.handle_left_click(col);
+                }
+                Mouse::RightClick(_, col) => {
+                    should_render = self.handle_right_click(col);
+                }
+                Mouse::Hold(_, col) if self.dragged_tab.is_some() => {
+                    let drop_target = get_clicked_tab(&self.tab_line, col)
+                        .filter(|t| Some(*t) != self.dragged_tab);
+                    should_render = self.drop_target != drop_target;
+                    self.drop_target = drop_target;
+                }
+                Mouse::Release(_, col) => {
+                    if let Some(dragged_tab) = self.dragged_tab.take() {
+                        self.drop_target = None;
+                        if let Some(target) = get_clicked_tab(&self.tab_line, col) {
+                            if target != dragged_tab {
+                                self.move_tab(dragged_tab, target);
+                            }
+                        }
+                        should_render = true;
                     }
                 }
+                Mouse::Hover(_, col) => {
+                    let is_hovered = get_clicked_line_part(&self.tab_line, col)
+                        .is_some_and(|p| p.segment == Some(Segment::Time));
+                    should_render = is_hovered != self.time_hovered_at.is_some();
+                    self.time_hovered_at = is_hovered.then(Instant::now);
+                }
                 Mouse::ScrollUp(_) => {
-                    switch_tab_to(min(self.active_tab_idx + 1, self.tabs.len()) as u32);
+                    self.scroll_tabs(true);
                 }
                 Mouse::ScrollDown(_) => {
-                    switch_tab_to(max(self.active_tab_idx.saturating_sub(1), 1) as u32);
+                    self.scroll_tabs(false);
                 }
                 _ => {}
             },
             Event::Timer(_) => {
                 set_timeout(0.5);
+                if self
+                    .time_hovered_at
+                    .is_some_and(|at| at.elapsed() >= TIME_HOVER_FOR)
+                {
+                    self.time_hovered_at = None;
+                }
+                self.notifications.tick(Instant::now());
+                self.progress
+                    .tick(Instant::now(), self.config.progress_timeout);
+                if self.needs_cwd() {
+                    self.cwd.tick(Instant::now());
+                }
+                for command in &mut self.commands {
+                    command.tick(Instant::now(), self.cwd.cwd());
+                }
+                if self.config.git_status {
+                    self.git.tick(Instant::now(), self.config.git_interval);
+                }
+                self.system_files.tick(
+                    Instant::now(),
+                    self.config.system_interval,
+                    &self.config.system_root,
+                    &self.system_file_patterns(),
+                );
+                if self.config.meeting_calendar.is_some() {
+                    self.meeting.tick(Utc::now(), self.config.meeting_days);
+                }
+                self.pomodoro
+                    .tick(Instant::now(), &self.config.pomodoro_config);
+                for name in self.timers.tick(Instant::now()) {
+                    self.notifications.push(
+                        format!("⏳ {} is done", name),
+                        Level::Warn,
+                        Duration::from_secs(10),
+                    );
+                }
+                self.disk
+                    .tick(Instant::now(), self.config.disk_interval, &self.config.disk);
                 should_render = true;
             }
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                if let Some(name) = context.get(COMMAND_CONTEXT_KEY) {
+                    if let Some(command) = self.commands.iter_mut().find(|c| c.name() == name) {
+                        should_render = command.handle_result(exit_code, &stdout, &stderr);
+                    }
+                } else if let Some(cwd) = context.get(GIT_CONTEXT_KEY) {
+                    should_render = self.git.handle_result(cwd, exit_code, &stdout);
+                } else if context.contains_key(KUBE_CONTEXT_KEY) {
+                    self.kube.handle_result(exit_code, &stdout);
+                    if !self.update_host_folder() {
+                        should_render = self.read_host_files();
+                    }
+                } else if context.contains_key(SYSTEM_CONTEXT_KEY) {
+                    self.system_files.handle_result(exit_code, &stdout, &stderr);
+                    self.sample_system();
+                    should_render = true;
+                } else if context.contains_key(DISK_CONTEXT_KEY) {
+                    self.disk.handle_result(exit_code, &stdout, &stderr);
+                    should_render = true;
+                } else if context.contains_key(MOVE_TAB_CONTEXT_KEY) && exit_code != Some(0) {
+                    let stderr = String::from_utf8_lossy(&stderr);
+                    let error = stderr.lines().next().unwrap_or("zellij failed");
+                    eprintln!("Failed to move the tab: {}", stderr);
+                    self.notifications.push(
+                        format!("Failed to move the tab: {}", error),
+                        Level::Error,
+                        Duration::from_secs(10),
+                    );
+                    should_render = true;
+                }
+            }
+            Event::HostFolderChanged(host_folder) => {
+                self.host_folder = Some(host_folder);
+                should_render = self.read_host_files();
+                // changes are watched for in the host folder at the time it's asked for
+                if self.watches_files() {
+                    watch_filesystem();
+                }
+            }
+            Event::FailedToChangeHostFolder(e) => {
+                eprintln!("Failed to change the host folder: {:?}", e);
+            }
+            Event::FileSystemCreate(paths)
+            | Event::FileSystemUpdate(paths)
+            | Event::FileSystemDelete(paths) => {
+                let paths: Vec<PathBuf> = paths.into_iter().map(|(path, _)| path).collect();
+                should_render = self.read_files(|file| file.is_changed_by(&paths));
+                if let Some(host_folder) = &self.host_folder {
+                    if self.kube.is_changed_by(&paths) {
+                        should_render |= self.kube.read(host_folder);
+                    }
+                    if self.meeting.is_changed_by(&paths) {
+                        self.meeting.read(host_folder);
+                        self.meeting.tick(Utc::now(), self.config.meeting_days);
+                        should_render = true;
+                    }
+                    if self.holidays.is_changed_by(&paths) {
+                        self.holidays.read(host_folder, today());
+                        should_render = true;
+                    }
+                }
+            }
+            Event::CustomMessage(name, payload) if name == "session_layout" => {
+                if self.cwd.update_layout(&payload) {
+                    if let Some(cwd) = self.cwd.cwd() {
+                        self.git.set_cwd(cwd);
+                    }
+                }
+            }
             _ => {
                 eprintln!("Got unrecognized event: {:?}", event);
             }
@@ -107,6 +377,32 @@ impl ZellijPlugin for State {
         should_render
     }
 
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        match PipeCommand::parse(&pipe_message) {
+            Some(Ok(command)) => {
+                let should_render = matches!(
+                    command,
+                    PipeCommand::SetConfig(_)
+                        | PipeCommand::Toggle(_)
+                        | PipeCommand::Notify { .. }
+                        | PipeCommand::DoNotDisturb(_)
+                        | PipeCommand::History
+                        | PipeCommand::Progress { .. }
+                        | PipeCommand::Pomodoro(_)
+                        | PipeCommand::Timer(_)
+                );
+                let result = self.run_pipe_command(command);
+                reply(&pipe_message, result);
+                should_render
+            }
+            Some(Err(e)) => {
+                reply(&pipe_message, Err(e));
+                false
+            }
+            None => false,
+        }
+    }
+
     fn render(&mut self, _rows: usize, cols: usize) {
         if self.tabs.is_empty() {
             return;
@@ -116,7 +412,8 @@ impl ZellijPlugin for State {
         let mut active_swap_layout_name = None;
         let mut is_swap_layout_dirty = false;
         let mut is_alternate_tab = false;
-        for t in &mut self.tabs {
+        let palette = self.palette();
+        for t in &self.tabs {
             let mut tabname = t.name.clone();
             if t.active && self.mode_info.mode == InputMode::RenameTab {
                 if tabname.is_empty() {
@@ -132,29 +429,37 @@ impl ZellijPlugin for State {
                 tabname,
                 t,
                 is_alternate_tab,
-                self.mode_info.style.colors,
+                TabMarkers {
+                    is_pending_close: self.pending_close_tab == Some(t.position),
+                    drop_marker: self.drop_marker(t.position),
+                    is_previous: self.tab_history.previous() == Some(t.position),
+                },
+                palette,
                 self.mode_info.capabilities,
             );
             is_alternate_tab = !is_alternate_tab;
             all_tabs.push(tab);
         }
-        self.tab_line = tab_line(
-            self.mode_info.session_name.as_deref(),
-            all_tabs,
-            active_tab_index,
+        let state = LineState {
+            session_name: self.mode_info.session_name.as_deref(),
             cols,
-            self.mode_info.style.colors,
-            self.mode_info.capabilities,
-            self.mode_info.style.hide_session_name,
-            self.mode_info.mode,
-            &active_swap_layout_name,
+            palette,
+            capabilities: self.mode_info.capabilities,
+            hide_session_name: self.mode_info.style.hide_session_name,
+            mode: self.mode_info.mode,
+            active_swap_layout_name: &active_swap_layout_name,
             is_swap_layout_dirty,
-        );
+            mode_picker_open: self.mode_picker_open,
+            is_time_alt: self.is_time_alt || self.time_hovered_at.is_some(),
+            holidays: &self.holidays,
+            config: &self.config,
+        };
+        self.tab_line = tab_line(state, all_tabs, active_tab_index, self.segments());
         let output = self
             .tab_line
             .iter()
             .fold(String::new(), |output, part| output + &part.part);
-        let background = self.mode_info.style.colors.text_unselected.background;
+        let background = palette.text_unselected.background;
         match background {
             PaletteColor::Rgb((r, g, b)) => {
                 print!("{}\u{1b}[48;2;{};{};{}m\u{1b}[0K", output, r, g, b);
@@ -165,3 +470,612 @@ impl ZellijPlugin for State {
         }
     }
 }
+
+impl State {
+    fn apply_configuration(&mut self) {
+        let config = Config::from_configuration(&self.configuration);
+        // command segments are only restarted if they changed, to keep their output
+        if config.commands != self.config.commands || self.commands.is_empty() {
+            self.commands = config
+                .commands
+                .iter()
+                .cloned()
+                .map(CommandSegment::new)
+                .collect();
+        }
+        if config.notification_history != self.config.notification_history {
+            self.notifications
+                .set_history_size(config.notification_history);
+        }
+        // new files are read once the host folder contains them
+        let mut should_read = false;
+        if config.files != self.config.files {
+            self.files = config.files.iter().cloned().map(FileSegment::new).collect();
+            should_read = true;
+        }
+        if (&config.kube_config, &config.kube_production)
+            != (&self.config.kube_config, &self.config.kube_production)
+        {
+            self.kube = Kube::new(
+                config.kube_config.clone(),
+                config.kube_production.as_deref(),
+            );
+        }
+        if config.meeting_calendar != self.config.meeting_calendar {
+            self.meeting = Meeting::new(config.meeting_calendar.clone());
+            should_read = true;
+        }
+        if (&config.holidays, config.holiday_days)
+            != (&self.config.holidays, self.config.holiday_days)
+        {
+            self.holidays = Holidays::new(config.holidays.clone(), config.holiday_days);
+            should_read = true;
+        }
+        if config.system_root != self.config.system_root {
+            self.system = SystemStats::default();
+            self.battery = Battery::default();
+            self.network = Network::default();
+            self.thermal = Thermal::default();
+        }
+        self.config = config;
+        self.request_permissions();
+        if self.got_permissions && !self.update_host_folder() && should_read {
+            self.read_host_files();
+        }
+    }
+
+    // asks again when the configuration needs permissions that weren't asked for yet
+    fn request_permissions(&mut self) {
+        let permissions = self.config.permissions();
+        if permissions
+            .iter()
+            .all(|p| self.requested_permissions.contains(p))
+        {
+            return;
+        }
+        for permission in &permissions {
+            if !self.requested_permissions.contains(permission) {
+                self.requested_permissions.push(*permission);
+            }
+        }
+        request_permission(&self.requested_permissions);
+    }
+
+    // plugins only see the directory zellij was started in, so the host folder is changed to the
+    // deepest folder containing the files that are read, which zellij watches for changes.
+    // Returns whether the host folder is changing, the files are read once it changed
+    fn update_host_folder(&mut self) -> bool {
+        if self.config.kube {
+            self.kube.find_paths();
+        }
+        let mut paths: Vec<&Path> = vec![];
+        paths.extend(self.config.files.iter().map(|file| file.path.as_path()));
+        paths.extend(self.kube.paths());
+        paths.extend(self.config.meeting_calendar.as_deref());
+        paths.extend(self.config.holidays.iter().map(|(_, path)| path.as_path()));
+        let Some(host_folder) = common_folder(paths) else {
+            return false;
+        };
+        if self.requested_host_folder.as_ref() == Some(&host_folder) {
+            return false;
+        }
+        self.requested_host_folder = Some(host_folder.clone());
+        change_host_folder(host_folder);
+        true
+    }
+
+    // the /proc and /sys files read by the segments that are shown
+    fn system_file_patterns(&self) -> Vec<&'static str> {
+        let mut patterns = vec![];
+        if self.config.cpu.is_some()
+            || self.config.memory.is_some()
+            || self.config.load.is_some()
+            || self.config.uptime
+        {
+            patterns.extend(PROC_FILES);
+        }
+        if self.config.network.is_some() {
+            patterns.push(NET_DEV);
+        }
+        if self.config.thermal {
+            patterns.extend(ZONE_FILES);
+        }
+        if self.config.battery {
+            patterns.extend(SUPPLY_FILES);
+        }
+        patterns
+    }
+
+    fn sample_system(&mut self) {
+        let files = &self.system_files;
+        self.system.sample(files);
+        self.network.sample(
+            Instant::now(),
+            files,
+            self.config.network_window,
+            self.config.network_interface.as_deref(),
+        );
+        self.thermal
+            .sample(files, self.config.thermal_zone.as_deref());
+        self.battery.sample(files);
+    }
+
+    fn watches_files(&self) -> bool {
+        !self.files.is_empty()
+            || self.config.kube
+            || self.config.meeting_calendar.is_some()
+            || !self.config.holidays.is_empty()
+    }
+
+    // returns whether any of the values changed
+    fn read_host_files(&mut self) -> bool {
+        let Some(host_folder) = &self.host_folder else {
+            return false;
+        };
+        let mut changed = false;
+        for file in &mut self.files {
+            changed |= file.read(host_folder);
+        }
+        changed |= self.kube.read(host_folder);
+        self.meeting.read(host_folder);
+        self.holidays.read(host_folder, today());
+        changed
+    }
+
+    // returns whether any of the values changed
+    fn read_files(&mut self, should_read: impl Fn(&FileSegment) -> bool) -> bool {
+        let Some(host_folder) = &self.host_folder else {
+            return false;
+        };
+        let mut changed = false;
+        for file in self.files.iter_mut().filter(|f| should_read(f)) {
+            changed |= file.read(host_folder);
+        }
+        changed
+    }
+
+    // the right side segments after the time, from the most to the least important
+    fn segments(&self) -> Vec<SegmentForms> {
+        let palette = self.palette();
+        let separator = tab_separator(self.mode_info.capabilities);
+        let mut segments = vec![];
+        if self.config.shows("notification") {
+            segments.extend(alone(self.notifications.render(palette, separator)));
+        }
+        if self.config.shows("progress") {
+            segments.extend(alone(self.progress.render(palette, separator)));
+        }
+        if let Some(display) = self.config.cpu.filter(|_| self.config.shows("cpu")) {
+            segments.extend(alone(self.system.render_cpu(display, palette, separator)));
+        }
+        if let Some(display) = self.config.memory.filter(|_| self.config.shows("memory")) {
+            segments.extend(alone(
+                self.system.render_memory(display, palette, separator),
+            ));
+        }
+        if let Some(display) = self.config.load.filter(|_| self.config.shows("load")) {
+            segments.extend(alone(self.system.render_load(display, palette, separator)));
+        }
+        if self.config.uptime && self.config.shows("uptime") {
+            segments.extend(alone(self.system.render_uptime(palette, separator)));
+        }
+        if self.config.shows("disk") {
+            segments.extend(alone(self.disk.render(
+                self.config.disk_thresholds,
+                palette,
+                separator,
+            )));
+        }
+        if self.config.thermal && self.config.shows("thermal") {
+            segments.extend(alone(self.thermal.render(
+                self.config.thermal_thresholds,
+                palette,
+                separator,
+            )));
+        }
+        if let Some(display) = self.config.network.filter(|_| self.config.shows("network")) {
+            segments.extend(alone(self.network.render(display, palette, separator)));
+        }
+        if self.config.battery && self.config.shows("battery") {
+            segments.extend(alone(self.battery.render(
+                self.config.battery_warn,
+                self.config.battery_critical,
+                palette,
+                separator,
+            )));
+        }
+        if self.config.pomodoro && self.config.shows("pomodoro") {
+            segments.push(vec![vec![self.pomodoro.render(
+                &self.config.pomodoro_config,
+                palette,
+                separator,
+            )]]);
+        }
+        if self.config.shows("timer") && !self.timers.is_empty() {
+            // all the timers when they fit, or only the one ending the soonest
+            let now = Instant::now();
+            let mut forms = vec![self.timers.render(now, palette, separator)];
+            forms.extend(
+                self.timers
+                    .render_collapsed(now, palette, separator)
+                    .map(|part| vec![part]),
+            );
+            segments.push(forms);
+        }
+        if self.config.meeting_calendar.is_some() && self.config.shows("meeting") {
+            segments.extend(alone(self.meeting.render(
+                Utc::now(),
+                chrono::Duration::from_std(self.config.meeting_warn).unwrap_or_default(),
+                palette,
+                separator,
+            )));
+        }
+        if let Some(location) = self.config.location.filter(|_| self.config.shows("sun")) {
+            segments.extend(alone(location.render(
+                Utc::now(),
+                self.config.moon_phase,
+                palette,
+                separator,
+            )));
+        }
+        if self.config.kube && self.config.shows("kube") {
+            segments.extend(alone(self.kube.render(palette, separator)));
+        }
+        if self.config.git_status && self.config.shows("git") {
+            segments.extend(alone(self.git.render(palette, separator)));
+        }
+        for command in &self.commands {
+            if self.config.shows(&command.id()) {
+                segments.extend(alone(command.render(palette, separator)));
+            }
+        }
+        for file in &self.files {
+            if self.config.shows(&file.id()) {
+                segments.extend(alone(file.render(palette, separator)));
+            }
+        }
+        if self.config.shows("notification") {
+            segments.extend(alone(self.notifications.render_badge(palette, separator)));
+        }
+        segments
+    }
+
+    // the theme's colors, or the night colors between sunset and sunrise
+    fn palette(&self) -> Styling {
+        let palette = self.mode_info.style.colors;
+        match self.config.location {
+            Some(location) if self.config.day_night_theme && location.is_night(Utc::now()) => {
+                self.config.night_colors.apply(palette)
+            }
+            _ => palette,
+        }
+    }
+
+    // the cwd of the focused pane is only tracked when something shows it
+    fn needs_cwd(&self) -> bool {
+        self.config.git_status || self.commands.iter().any(|c| c.uses_cwd())
+    }
+
+    fn run_pipe_command(&mut self, command: PipeCommand) -> Result<String, String> {
+        match command {
+            PipeCommand::LastTab => {
+                let position = self
+                    .tab_history
+                    .previous()
+                    .ok_or_else(|| "no previous tab".to_string())?;
+                switch_tab_to(position as u32 + 1);
+                Ok(self.tabs[position].name.clone())
+            }
+            PipeCommand::FocusTab(name) => {
+                let tab = self
+                    .tabs
+                    .iter()
+                    .find(|t| name.is_match(&t.name))
+                    .ok_or_else(|| format!("no tab matches {}", name))?;
+                switch_tab_to(tab.position as u32 + 1);
+                Ok(tab.name.clone())
+            }
+            PipeCommand::FocusOrCreate(name) => {
+                focus_or_create_tab(&name);
+                Ok(name)
+            }
+            PipeCommand::SetConfig(changes) => {
+                self.configuration.extend(changes);
+                self.apply_configuration();
+                Ok("ok".to_string())
+            }
+            PipeCommand::Toggle(segment_id) => {
+                let mut hidden_segments = self.config.hidden_segments.clone();
+                let is_shown = !hidden_segments.remove(&segment_id);
+                if is_shown {
+                    hidden_segments.insert(segment_id);
+                }
+                self.configuration.insert(
+                    "hidden_segments".to_string(),
+                    hidden_segments.into_iter().collect::<Vec<_>>().join(" "),
+                );
+                self.apply_configuration();
+                Ok(if is_shown { "hidden" } else { "shown" }.to_string())
+            }
+            PipeCommand::Reload => {
+                reload_plugin_with_id(get_plugin_ids().plugin_id);
+                Ok("reloading".to_string())
+            }
+            PipeCommand::Notify { text, level, ttl } => {
+                self.notifications.push(text, level, ttl);
+                Ok("ok".to_string())
+            }
+            PipeCommand::DoNotDisturb(do_not_disturb) => {
+                let do_not_disturb =
+                    do_not_disturb.unwrap_or(!self.notifications.is_do_not_disturb());
+                self.notifications.set_do_not_disturb(do_not_disturb);
+                Ok(if do_not_disturb { "on" } else { "off" }.to_string())
+            }
+            PipeCommand::History => {
+                let history = self.notifications.history_text();
+                self.notifications.mark_all_read();
+                Ok(history)
+            }
+            PipeCommand::Progress { id, value, label } => {
+                self.progress.update(id, value, label);
+                Ok("ok".to_string())
+            }
+            PipeCommand::Pomodoro(action) => {
+                let config = &self.config.pomodoro_config;
+                match action {
+                    PomodoroAction::Start => self.pomodoro.start(config),
+                    PomodoroAction::Pause => self.pomodoro.pause(),
+                    PomodoroAction::Toggle => self.pomodoro.toggle(config),
+                    PomodoroAction::Reset => self.pomodoro.reset(),
+                    PomodoroAction::Skip => self.pomodoro.skip(config),
+                    PomodoroAction::Status => {}
+                }
+                Ok(self.pomodoro.status(config))
+            }
+            PipeCommand::Timer(action) => match action {
+                TimerAction::Start { length, label } => {
+                    self.timers.start(Instant::now(), length, label);
+                    Ok("ok".to_string())
+                }
+                TimerAction::Stop(selector) => match self.timers.stop(&selector) {
+                    0 => Err("no matching timer".to_string()),
+                    count => Ok(format!("{} stopped", count)),
+                },
+                TimerAction::Pause(selector) => {
+                    match self.timers.toggle_pause(Instant::now(), &selector) {
+                        0 => Err("no matching timer".to_string()),
+                        count => Ok(format!("{} paused or resumed", count)),
+                    }
+                }
+                TimerAction::List => Ok(self.timers.list(Instant::now())),
+            },
+            PipeCommand::State { as_text: true } => Ok(bar_text(&self.tab_line)),
+            PipeCommand::State { as_text: false } => Ok(bar_state(
+                &self.tabs,
+                &self.mode_info,
+                &self.tab_line,
+                self.tab_history.previous(),
+            )
+            .to_string()),
+        }
+    }
+
+    fn handle_left_click(&mut self, col: usize) -> bool {
+        let clicked_segment = get_clicked_line_part(&self.tab_line, col).and_then(|p| p.segment);
+        let should_render = self.pending_close_tab.take().is_some();
+        self.dragged_tab = get_clicked_tab(&self.tab_line, col).filter(|_| self.config.drag_tabs);
+        if self.mode_picker_open {
+            // any click closes the picker, picking a mode if it was on one
+            self.mode_picker_open = false;
+            if let Some(Segment::ModeChoice(mode)) = clicked_segment {
+                switch_to_input_mode(&mode);
+            }
+            return true;
+        }
+        match clicked_segment {
+            Some(Segment::SwapLayout { is_dirty: true }) => restore_swap_layout(),
+            Some(Segment::SwapLayout { is_dirty: false }) => next_swap_layout(),
+            Some(Segment::Mode) => {
+                if self.mode_info.mode == InputMode::Locked {
+                    switch_to_input_mode(&InputMode::Normal);
+                } else {
+                    switch_to_input_mode(&InputMode::Locked);
+                }
+            }
+            Some(Segment::NewTab) => self.open_new_tab(),
+            Some(Segment::Notification(id)) => {
+                self.notifications.dismiss(id);
+                return true;
+            }
+            Some(Segment::NotificationBadge) => {
+                let do_not_disturb = !self.notifications.is_do_not_disturb();
+                self.notifications.set_do_not_disturb(do_not_disturb);
+                return true;
+            }
+            Some(Segment::Pomodoro) => {
+                self.pomodoro.toggle(&self.config.pomodoro_config);
+                return true;
+            }
+            Some(Segment::Time) => {
+                match self.config.time_click {
+                    TimeClick::Timer => self.timers.start(Instant::now(), None, None),
+                    TimeClick::Calendar => self.is_time_alt = !self.is_time_alt,
+                }
+                return true;
+            }
+            Some(Segment::Timer(id)) => {
+                self.timers.click(Instant::now(), id);
+                return true;
+            }
+            Some(Segment::ModeChoice(_)) => {}
+            Some(Segment::MoreTabs) | None => {
+                let tab_to_focus = get_tab_to_focus(&self.tab_line, self.active_tab_idx, col);
+                if let Some(idx) = tab_to_focus {
+                    switch_tab_to(idx.try_into().unwrap());
+                }
+            }
+        }
+        should_render
+    }
+
+    fn handle_right_click(&mut self, col: usize) -> bool {
+        let clicked_segment = get_clicked_line_part(&self.tab_line, col).and_then(|p| p.segment);
+        if self.mode_picker_open {
+            self.mode_picker_open = false;
+            return true;
+        }
+        match clicked_segment {
+            Some(Segment::SwapLayout { is_dirty: true }) => restore_swap_layout(),
+            Some(Segment::SwapLayout { is_dirty: false }) => previous_swap_layout(),
+            Some(Segment::Mode) => {
+                self.mode_picker_open = true;
+                return true;
+            }
+            Some(Segment::NotificationBadge) => {
+                self.notifications.mark_all_read();
+                return true;
+            }
+            Some(Segment::Pomodoro) => {
+                self.pomodoro.reset();
+                return true;
+            }
+            Some(Segment::Time) if self.config.time_click == TimeClick::Timer => {
+                self.timers
+                    .start(Instant::now(), Some(self.config.timer_countdown), None);
+                return true;
+            }
+            Some(Segment::Timer(id)) => {
+                let selector = TimerSelector {
+                    id: Some(id),
+                    label: None,
+                };
+                self.timers.stop(&selector);
+                return true;
+            }
+            None => {
+                if let Some(position) = get_clicked_tab(&self.tab_line, col) {
+                    return self.close_tab(position);
+                }
+            }
+            _ => {}
+        }
+        self.pending_close_tab.take().is_some()
+    }
+
+    fn scroll_tabs(&mut self, forward: bool) {
+        let active_position = self.active_tab_idx.saturating_sub(1);
+        let order = match self.config.scroll_order {
+            ScrollOrder::Position => (0..self.tabs.len()).collect(),
+            ScrollOrder::Mru => {
+                if self.scroll_cycle.is_empty() {
+                    self.scroll_cycle = self.tab_history.order(self.tabs.len());
+                }
+                self.scroll_cycle.clone()
+            }
+        };
+        let current = match order.iter().position(|p| *p == active_position) {
+            Some(current) => current,
+            None => return,
+        };
+        let next = if forward != self.config.scroll_reverse {
+            if current + 1 < order.len() {
+                current + 1
+            } else if self.config.scroll_wrap {
+                0
+            } else {
+                current
+            }
+        } else if current > 0 {
+            current - 1
+        } else if self.config.scroll_wrap {
+            order.len() - 1
+        } else {
+            current
+        };
+        if next != current {
+            self.scroll_target = Some(order[next]);
+            switch_tab_to(order[next] as u32 + 1);
+        }
+    }
+
+    fn open_new_tab(&self) {
+        match &self.config.new_tab_layout {
+            Some(layout) if layout.ends_with(".kdl") => {
+                new_tabs_with_layout_info(LayoutInfo::File(layout.clone()))
+            }
+            Some(layout) => new_tabs_with_layout_info(LayoutInfo::BuiltIn(layout.clone())),
+            None => new_tab(None, self.config.new_tab_cwd.as_deref()),
+        }
+    }
+
+    // tabs with open terminals are only closed after a second click, returns whether the
+    // confirmation needs to be rendered
+    fn close_tab(&mut self, position: usize) -> bool {
+        if self.pending_close_tab == Some(position) || !self.has_open_terminals(position) {
+            self.pending_close_tab = None;
+            close_tab_with_index(position);
+            false
+        } else {
+            self.pending_close_tab = Some(position);
+            true
+        }
+    }
+
+    fn drop_marker(&self, tab_position: usize) -> Option<DropMarker> {
+        match (self.dragged_tab, self.drop_target) {
+            (Some(dragged_tab), Some(drop_target)) if drop_target == tab_position => {
+                if dragged_tab < drop_target {
+                    Some(DropMarker::After)
+                } else {
+                    Some(DropMarker::Before)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // plugins can't move tabs, so the dragged tab is focused and moved through the cli, which
+    // moves the focused tab of the client it picks
+    fn move_tab(&self, from: usize, to: usize) {
+        let session_name = match self.mode_info.session_name.as_deref() {
+            Some(session_name) => session_name,
+            None => return,
+        };
+        let direction = if from < to { "right" } else { "left" };
+        let script = format!(
+            "for _ in $(seq {}); do zellij --session \"$0\" action move-tab {} || exit; done",
+            from.abs_diff(to),
+            direction
+        );
+        switch_tab_to(from as u32 + 1);
+        let mut context = BTreeMap::new();
+        context.insert(MOVE_TAB_CONTEXT_KEY.to_string(), String::new());
+        run_command(&["sh", "-c", &script, session_name], context);
+    }
+
+    // plugins can't see what runs in a shell, so any terminal that hasn't exited may be busy
+    fn has_open_terminals(&self, tab_position: usize) -> bool {
+        self.panes
+            .panes
+            .get(&tab_position)
+            .map(|panes| panes.iter().any(|p| !p.is_plugin && !p.exited))
+            .unwrap_or(false)
+    }
+}
+
+// the day in the timezone of the clock
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&TIMEZONE).date_naive()
+}
+
+// segments that are shown as they are or not at all
+fn alone(parts: impl IntoIterator<Item = LinePart>) -> impl Iterator<Item = SegmentForms> {
+    parts.into_iter().map(|part| vec![vec![part]])
+}
+
+// zellij has no action to undo manual changes to a swap layout, but moving away from it and back
+// re-applies it from scratch
+fn restore_swap_layout() {
+    next_swap_layout();
+    previous_swap_layout();
+}
@@ -0,0 +1,809 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{
+    DateTime, Datelike, Duration, Locale, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use zellij_tile::prelude::*;
+
+use crate::command::truncate;
+use crate::file::is_changed;
+use crate::line::{strip_ansi, TIMEZONE};
+use crate::system::{host_path, render};
+use crate::LinePart;
+
+// events are shown as starting for this long after their start
+const STARTING_FOR: Duration = Duration::minutes(5);
+// occurrences are expanded again this often, and whenever the calendar changes
+const EXPAND_EVERY: Duration = Duration::hours(1);
+// recurring events stop being expanded after this many periods, in case the rule never ends
+const MAX_PERIODS: i64 = 100_000;
+const MAX_SUMMARY_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// the parts of RRULE that calendars use for meetings
+#[derive(Debug, Clone)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    // weekdays, with their position in the month for monthly rules, eg. `-1FR`
+    by_day: Vec<(Option<i32>, Weekday)>,
+    // negative days count from the end of the month
+    by_month_day: Vec<i32>,
+    // 1 to 12
+    by_month: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Event {
+    uid: Option<String>,
+    summary: String,
+    // in the timezone of zone, so recurrences keep their time across daylight saving changes
+    start: Option<NaiveDateTime>,
+    zone: Option<Tz>,
+    is_all_day: bool,
+    is_cancelled: bool,
+    rule: Option<Rule>,
+    // raw RRULE, parsed once the start is known since UNTIL can be in its timezone
+    rrule: Option<String>,
+    exdates: Vec<DateTime<Utc>>,
+    // set on events replacing one occurrence of a recurring event
+    recurrence_id: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct Occurrence {
+    start: DateTime<Utc>,
+    summary: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Meeting {
+    path: Option<PathBuf>,
+    events: Vec<Event>,
+    // the occurrences of the coming days, soonest first
+    occurrences: Vec<Occurrence>,
+    expanded_at: Option<DateTime<Utc>>,
+}
+
+impl Meeting {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Meeting {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_changed_by(&self, changed_paths: &[PathBuf]) -> bool {
+        self.path
+            .as_ref()
+            .is_some_and(|path| is_changed(path, changed_paths))
+    }
+
+    pub fn read(&mut self, host_folder: &Path) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.events = match fs::read_to_string(host_path(host_folder, path)) {
+            Ok(calendar) => parse_calendar(&calendar),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                vec![]
+            }
+        };
+        self.expanded_at = None;
+    }
+
+    // expands the recurring events for the coming days when it's due
+    pub fn tick(&mut self, now: DateTime<Utc>, days: i64) {
+        if self
+            .expanded_at
+            .is_some_and(|expanded_at| now - expanded_at < EXPAND_EVERY)
+        {
+            return;
+        }
+        self.expanded_at = Some(now);
+        self.occurrences = upcoming(&self.events, now - STARTING_FOR, now + Duration::days(days));
+    }
+
+    pub fn render(
+        &self,
+        now: DateTime<Utc>,
+        warn_before: Duration,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let next = self
+            .occurrences
+            .iter()
+            .find(|o| o.start + STARTING_FOR > now)?;
+        let until_start = next.start - now;
+        let when = if until_start <= Duration::zero() {
+            "now".to_string()
+        } else if until_start < Duration::hours(24) {
+            // rounded up, so it's never `in 0m` before the start
+            let minutes = (until_start.num_seconds() + 59) / 60;
+            if minutes < 60 {
+                format!("in {}m", minutes)
+            } else {
+                format!("in {}h {}m", minutes / 60, minutes % 60)
+            }
+        } else {
+            next.start
+                .with_timezone(&TIMEZONE)
+                .format_localized("%a %H:%M", Locale::ja_JP)
+                .to_string()
+        };
+        let text = format!("{} {}", truncate(&next.summary, MAX_SUMMARY_WIDTH), when);
+        let bg = if until_start <= warn_before {
+            palette.exit_code_error.base
+        } else {
+            palette.ribbon_unselected.background
+        };
+        Some(render(&text, bg, palette, separator))
+    }
+}
+
+// the days of the all day events between from and to, with their summary, for calendars of
+// holidays
+pub fn all_day_events(calendar: &str, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, String)> {
+    // the days start at different times in the timezones of the events
+    let day = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let (from_utc, to_utc) = (day(from) - Duration::days(1), day(to) + Duration::days(1));
+    parse_calendar(calendar)
+        .iter()
+        .filter(|e| !e.is_cancelled && e.is_all_day)
+        .flat_map(|event| {
+            let zone = event.zone.unwrap_or(TIMEZONE);
+            occurrences(event, from_utc, to_utc)
+                .into_iter()
+                .map(move |start| start.with_timezone(&zone).date_naive())
+                .filter(|date| (from..=to).contains(date))
+                .map(move |date| (date, event.summary.clone()))
+        })
+        .collect()
+}
+
+// the occurrences starting between from and to, soonest first
+fn upcoming(events: &[Event], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Occurrence> {
+    // occurrences replaced by another event, by uid
+    let mut replaced: BTreeMap<&str, Vec<DateTime<Utc>>> = BTreeMap::new();
+    for event in events {
+        if let (Some(uid), Some(recurrence_id)) = (&event.uid, event.recurrence_id) {
+            replaced.entry(uid).or_default().push(recurrence_id);
+        }
+    }
+    let mut occurrences: Vec<Occurrence> = events
+        .iter()
+        .filter(|e| !e.is_cancelled && !e.is_all_day)
+        .flat_map(|event| {
+            let replaced = match (&event.uid, event.recurrence_id) {
+                (Some(uid), None) => replaced.get(uid.as_str()).map(Vec::as_slice),
+                _ => None,
+            };
+            occurrences(event, from, to)
+                .into_iter()
+                .filter(move |start| !replaced.unwrap_or(&[]).contains(start))
+                .map(move |start| Occurrence {
+                    start,
+                    summary: event.summary.clone(),
+                })
+        })
+        .collect();
+    occurrences.sort_by_key(|o| o.start);
+    occurrences
+}
+
+fn occurrences(event: &Event, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let (Some(start), Some(zone)) = (event.start, event.zone) else {
+        return vec![];
+    };
+    let in_range = |t: &DateTime<Utc>| *t >= from && *t <= to && !event.exdates.contains(t);
+    let Some(rule) = &event.rule else {
+        return to_utc(start, zone).filter(in_range).into_iter().collect();
+    };
+
+    let last_date = to.with_timezone(&zone).date_naive();
+    let first_date = start.date();
+    let mut occurrences = vec![];
+    let mut count = 0;
+    for period in 0..MAX_PERIODS {
+        // the rule is done once the periods are past what dates can be
+        let Some(n) = period.checked_mul(rule.interval) else {
+            break;
+        };
+        // the first day of the period, the rule is done once it's past the range
+        let (period_start, dates) = match rule.frequency {
+            Frequency::Daily => {
+                let Some(date) =
+                    Duration::try_days(n).and_then(|d| first_date.checked_add_signed(d))
+                else {
+                    break;
+                };
+                (date, vec![date])
+            }
+            Frequency::Weekly => {
+                let weekday = Duration::days(first_date.weekday().num_days_from_monday() as i64);
+                let Some(week_start) = Duration::try_weeks(n)
+                    .and_then(|w| (first_date - weekday).checked_add_signed(w))
+                else {
+                    break;
+                };
+                let dates = if rule.by_day.is_empty() {
+                    vec![week_start + weekday]
+                } else {
+                    rule.by_day
+                        .iter()
+                        .map(|(_, weekday)| {
+                            week_start + Duration::days(weekday.num_days_from_monday() as i64)
+                        })
+                        .collect()
+                };
+                (week_start, dates)
+            }
+            Frequency::Monthly => {
+                let Some(months) = (first_date.month0() as i64).checked_add(n) else {
+                    break;
+                };
+                let Some(year) = i32::try_from(months / 12)
+                    .ok()
+                    .and_then(|years| first_date.year().checked_add(years))
+                else {
+                    break;
+                };
+                let month = (months % 12) as u32 + 1;
+                let Some(month_start) = NaiveDate::from_ymd_opt(year, month, 1) else {
+                    break;
+                };
+                (
+                    month_start,
+                    month_dates(rule, year, month, first_date.day()),
+                )
+            }
+            Frequency::Yearly => {
+                let Some(year) = i32::try_from(n)
+                    .ok()
+                    .and_then(|years| first_date.year().checked_add(years))
+                else {
+                    break;
+                };
+                let Some(year_start) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+                    break;
+                };
+                // in the month of the first one unless the months are given
+                let dates = if rule.by_month.is_empty() {
+                    NaiveDate::from_ymd_opt(year, first_date.month(), first_date.day())
+                        .into_iter()
+                        .collect()
+                } else {
+                    rule.by_month
+                        .iter()
+                        .flat_map(|month| month_dates(rule, year, *month, first_date.day()))
+                        .collect()
+                };
+                (year_start, dates)
+            }
+        };
+        if period_start > last_date {
+            break;
+        }
+        let mut dates = dates;
+        // the months limit the dates of the other frequencies
+        if !rule.by_month.is_empty() {
+            dates.retain(|date| rule.by_month.contains(&date.month()));
+        }
+        dates.sort();
+        dates.dedup();
+        for date in dates {
+            let naive = date.and_time(start.time());
+            if naive < start {
+                continue;
+            }
+            count += 1;
+            if rule.count.is_some_and(|c| count > c) {
+                return occurrences;
+            }
+            let Some(occurrence) = to_utc(naive, zone) else {
+                continue;
+            };
+            if rule.until.is_some_and(|until| occurrence > until) || occurrence > to {
+                return occurrences;
+            }
+            if in_range(&occurrence) {
+                occurrences.push(occurrence);
+            }
+        }
+    }
+    occurrences
+}
+
+// the days of a month a monthly rule, or a yearly one in the month, falls on. Day is the day of
+// the month of the first one
+fn month_dates(rule: &Rule, year: i32, month: u32, day: u32) -> Vec<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1);
+    let Some(first) = first else {
+        return vec![];
+    };
+    let days_in_month = (28..=31)
+        .rev()
+        .find(|d| NaiveDate::from_ymd_opt(year, month, *d).is_some())
+        .unwrap_or(28) as i32;
+    let nth_day = |d: i32| {
+        let d = if d < 0 { days_in_month + 1 + d } else { d };
+        Some(d)
+            .filter(|d| (1..=days_in_month).contains(d))
+            .and_then(|d| NaiveDate::from_ymd_opt(year, month, d as u32))
+    };
+    if !rule.by_day.is_empty() {
+        rule.by_day
+            .iter()
+            .flat_map(|(position, weekday)| {
+                let offset = (7 + weekday.num_days_from_monday() as i32
+                    - first.weekday().num_days_from_monday() as i32)
+                    % 7;
+                // every matching weekday of the month
+                let days: Vec<i32> = (0..5)
+                    .map(|week| 1 + offset + week * 7)
+                    .filter(|d| *d <= days_in_month)
+                    .collect();
+                let days = match position {
+                    Some(position) if *position > 0 => days
+                        .get(*position as usize - 1)
+                        .copied()
+                        .into_iter()
+                        .collect(),
+                    Some(position) => days
+                        .len()
+                        .checked_sub(position.unsigned_abs() as usize)
+                        .map(|i| days[i])
+                        .into_iter()
+                        .collect(),
+                    None => days,
+                };
+                days.into_iter().filter_map(nth_day)
+            })
+            .collect()
+    } else if !rule.by_month_day.is_empty() {
+        rule.by_month_day
+            .iter()
+            .filter_map(|d| nth_day(*d))
+            .collect()
+    } else {
+        nth_day(day as i32).into_iter().collect()
+    }
+}
+
+// local times skipped by a daylight saving change are moved past it
+fn to_utc(naive: NaiveDateTime, zone: Tz) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            zone.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn parse_calendar(calendar: &str) -> Vec<Event> {
+    // long lines are folded, continuation lines start with a space or a tab
+    let mut lines: Vec<String> = vec![];
+    for line in calendar.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(continuation) if !lines.is_empty() => {
+                lines.last_mut().unwrap().push_str(continuation);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = vec![];
+    let mut event: Option<Event> = None;
+    // components inside events, like alarms, have their own properties
+    let mut nested = 0;
+    for line in &lines {
+        let Some((name, params, value)) = parse_property(line) else {
+            continue;
+        };
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => event = Some(Event::default()),
+            ("END", "VEVENT") => {
+                if let Some(mut event) = event.take() {
+                    if let (Some(rrule), Some(zone)) = (&event.rrule, event.zone) {
+                        event.rule = parse_rule(rrule, zone);
+                    }
+                    events.push(event);
+                }
+            }
+            ("BEGIN", _) if event.is_some() => nested += 1,
+            ("END", _) if event.is_some() => nested -= 1,
+            _ => {}
+        }
+        let Some(event) = event.as_mut().filter(|_| nested == 0) else {
+            continue;
+        };
+        match name.as_str() {
+            "UID" => event.uid = Some(value.to_string()),
+            "SUMMARY" => event.summary = strip_ansi(&unescape(value)),
+            "STATUS" => event.is_cancelled = value == "CANCELLED",
+            "RRULE" => event.rrule = Some(value.to_string()),
+            "DTSTART" => {
+                if let Some((start, zone, is_date)) = parse_date_time(value, &params) {
+                    event.start = Some(start);
+                    event.zone = Some(zone);
+                    event.is_all_day = is_date;
+                }
+            }
+            "EXDATE" => {
+                event.exdates.extend(value.split(',').filter_map(|value| {
+                    let (exdate, zone, _) = parse_date_time(value, &params)?;
+                    to_utc(exdate, zone)
+                }));
+            }
+            "RECURRENCE-ID" => {
+                event.recurrence_id = parse_date_time(value, &params)
+                    .and_then(|(recurrence_id, zone, _)| to_utc(recurrence_id, zone));
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+// `NAME;PARAM=value;PARAM="quoted:value":VALUE`
+fn parse_property(line: &str) -> Option<(String, BTreeMap<String, String>, &str)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        Some(i).filter(|_| c == ':' && !in_quotes)
+    })?;
+    let (name_and_params, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = name_and_params.split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+    Some((name, params, value.trim_end()))
+}
+
+// `20260102T090000Z` in utc, `20260102T090000` in the TZID timezone or the local one, and
+// `20260102` for all day events
+fn parse_date_time(
+    value: &str,
+    params: &BTreeMap<String, String>,
+) -> Option<(NaiveDateTime, Tz, bool)> {
+    let zone = || {
+        params
+            .get("TZID")
+            .and_then(|tz| tz.parse::<Tz>().ok())
+            .unwrap_or(TIMEZONE)
+    };
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date_time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        Some((date_time, Tz::UTC, false))
+    } else if value.contains('T') {
+        let date_time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Some((date_time, zone(), false))
+    } else {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        Some((date.and_hms_opt(0, 0, 0)?, zone(), true))
+    }
+}
+
+// `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20261231T000000Z`, none for the parts that aren't
+// supported, like BYSETPOS or BYHOUR, rather than showing occurrences that don't happen
+fn parse_rule(rrule: &str, zone: Tz) -> Option<Rule> {
+    const SUPPORTED: [&str; 8] = [
+        "FREQ",
+        "INTERVAL",
+        "COUNT",
+        "UNTIL",
+        "BYDAY",
+        "BYMONTHDAY",
+        "BYMONTH",
+        // the week start only matters for parts that aren't supported
+        "WKST",
+    ];
+    let parts: BTreeMap<&str, &str> = rrule.split(';').filter_map(|p| p.split_once('=')).collect();
+    if let Some(part) = parts.keys().find(|part| !SUPPORTED.contains(part)) {
+        eprintln!("Unsupported {} in RRULE: {}", part, rrule);
+        return None;
+    }
+    let frequency = match *parts.get("FREQ")? {
+        "DAILY" => Frequency::Daily,
+        "WEEKLY" => Frequency::Weekly,
+        "MONTHLY" => Frequency::Monthly,
+        "YEARLY" => Frequency::Yearly,
+        _ => return None,
+    };
+    let until = parts.get("UNTIL").and_then(|until| {
+        let (until, until_zone, is_date) = parse_date_time(until, &BTreeMap::new())?;
+        // a date includes the whole day, times without Z are in the timezone of the event
+        let until = if is_date {
+            until + Duration::days(1) - Duration::seconds(1)
+        } else {
+            until
+        };
+        to_utc(
+            until,
+            if until_zone == Tz::UTC {
+                until_zone
+            } else {
+                zone
+            },
+        )
+    });
+    let by_day: Vec<_> = parts
+        .get("BYDAY")
+        .map(|days| days.split(',').filter_map(parse_weekday).collect())
+        .unwrap_or_default();
+    let by_month_day: Vec<_> = parts
+        .get("BYMONTHDAY")
+        .map(|days| days.split(',').filter_map(|d| d.parse().ok()).collect())
+        .unwrap_or_default();
+    let by_month: Vec<u32> = parts
+        .get("BYMONTH")
+        .map(|months| {
+            months
+                .split(',')
+                .filter_map(|m| m.parse().ok())
+                .filter(|m| (1..=12).contains(m))
+                .collect()
+        })
+        .unwrap_or_default();
+    // the days of yearly rules are only looked for in the given months
+    if frequency == Frequency::Yearly
+        && by_month.is_empty()
+        && !(by_day.is_empty() && by_month_day.is_empty())
+    {
+        eprintln!("Unsupported RRULE without BYMONTH: {}", rrule);
+        return None;
+    }
+    Some(Rule {
+        frequency,
+        interval: parts
+            .get("INTERVAL")
+            .and_then(|i| i.parse().ok())
+            .filter(|i| *i > 0)
+            .unwrap_or(1),
+        count: parts.get("COUNT").and_then(|c| c.parse().ok()),
+        until,
+        by_day,
+        by_month_day,
+        by_month,
+    })
+}
+
+// `MO`, or with a position in the month like `2TU` or `-1FR`
+fn parse_weekday(day: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = day.len().checked_sub(2)?;
+    let (position, weekday) = day.split_at(split);
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let position = if position.is_empty() {
+        None
+    } else {
+        Some(position.trim_start_matches('+').parse().ok()?).filter(|p| *p != 0)
+    };
+    Some((position, weekday))
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                chars.next();
+                unescaped.push(' ');
+            }
+            ('\\', Some(escaped)) => {
+                chars.next();
+                unescaped.push(escaped);
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &[&str]) -> String {
+        let events: String = events
+            .iter()
+            .map(|e| format!("BEGIN:VEVENT\n{}\nEND:VEVENT\n", e))
+            .collect();
+        format!("BEGIN:VCALENDAR\n{}END:VCALENDAR\n", events)
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(time, "%Y%m%dT%H%M%S")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn expand(event: &str, from: &str, to: &str) -> Vec<DateTime<Utc>> {
+        let events = parse_calendar(&calendar(&[event]));
+        occurrences(&events[0], utc(from), utc(to))
+    }
+
+    fn rule(rrule: &str) -> Rule {
+        parse_rule(rrule, Tz::UTC).unwrap()
+    }
+
+    #[test]
+    fn rules() {
+        let parsed = rule("FREQ=MONTHLY;INTERVAL=2;BYDAY=2MO,-1FR;WKST=MO;COUNT=4");
+        assert_eq!(parsed.frequency, Frequency::Monthly);
+        assert_eq!(parsed.interval, 2);
+        assert_eq!(parsed.count, Some(4));
+        assert_eq!(
+            parsed.by_day,
+            vec![(Some(2), Weekday::Mon), (Some(-1), Weekday::Fri)]
+        );
+        let parsed = rule("FREQ=YEARLY;BYMONTH=1,13;BYDAY=2MO;UNTIL=20301231");
+        assert_eq!(parsed.by_month, vec![1]);
+        assert_eq!(parsed.until, Some(utc("20301231T235959")));
+        assert!(parse_rule("FREQ=MONTHLY;BYDAY=MO,TU;BYSETPOS=-1", Tz::UTC).is_none());
+        assert!(parse_rule("FREQ=DAILY;BYHOUR=9,17", Tz::UTC).is_none());
+        assert!(parse_rule("FREQ=YEARLY;BYDAY=20MO", Tz::UTC).is_none());
+        assert!(parse_rule("FREQ=HOURLY", Tz::UTC).is_none());
+    }
+
+    #[test]
+    fn positioned_weekdays() {
+        let day = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let dates = month_dates(&rule("FREQ=MONTHLY;BYDAY=2MO,-1FR"), 2026, 3, 1);
+        assert_eq!(dates, vec![day(9), day(27)]);
+        let dates = month_dates(&rule("FREQ=MONTHLY;BYDAY=5MO"), 2026, 3, 1);
+        assert_eq!(dates, vec![day(30)]);
+        assert!(month_dates(&rule("FREQ=MONTHLY;BYDAY=5TH"), 2026, 3, 1).is_empty());
+    }
+
+    #[test]
+    fn month_days_from_the_end() {
+        let parsed = rule("FREQ=MONTHLY;BYMONTHDAY=-1,31");
+        assert_eq!(
+            month_dates(&parsed, 2026, 2, 1),
+            vec![NaiveDate::from_ymd_opt(2026, 2, 28).unwrap()]
+        );
+        assert_eq!(
+            month_dates(&parsed, 2028, 2, 1),
+            vec![NaiveDate::from_ymd_opt(2028, 2, 29).unwrap()]
+        );
+        // a monthly rule on the 31st skips shorter months
+        let parsed = rule("FREQ=MONTHLY");
+        assert!(month_dates(&parsed, 2026, 4, 31).is_empty());
+    }
+
+    #[test]
+    fn count_and_until() {
+        let starts = expand(
+            "DTSTART:20260105T090000Z\nRRULE:FREQ=DAILY;COUNT=3",
+            "20260101T000000",
+            "20260201T000000",
+        );
+        assert_eq!(
+            starts,
+            vec![
+                utc("20260105T090000"),
+                utc("20260106T090000"),
+                utc("20260107T090000")
+            ]
+        );
+        let starts = expand(
+            "DTSTART:20260105T090000Z\nRRULE:FREQ=WEEKLY;BYDAY=MO,TH;UNTIL=20260112T090000Z",
+            "20260101T000000",
+            "20260201T000000",
+        );
+        assert_eq!(
+            starts,
+            vec![
+                utc("20260105T090000"),
+                utc("20260108T090000"),
+                utc("20260112T090000")
+            ]
+        );
+    }
+
+    #[test]
+    fn excluded_and_replaced_occurrences() {
+        let recurring = "UID:standup\nSUMMARY:standup\nDTSTART:20260105T090000Z\n\
+                         RRULE:FREQ=DAILY;COUNT=4\nEXDATE:20260107T090000Z";
+        let moved = "UID:standup\nSUMMARY:moved\nRECURRENCE-ID:20260106T090000Z\n\
+                     DTSTART:20260106T150000Z";
+        let events = parse_calendar(&calendar(&[recurring, moved]));
+        let upcoming: Vec<_> = upcoming(&events, utc("20260101T000000"), utc("20260201T000000"))
+            .into_iter()
+            .map(|o| (o.start, o.summary))
+            .collect();
+        assert_eq!(
+            upcoming,
+            vec![
+                (utc("20260105T090000"), "standup".to_string()),
+                (utc("20260106T150000"), "moved".to_string()),
+                (utc("20260108T090000"), "standup".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn daylight_saving_keeps_the_local_time() {
+        let starts = expand(
+            "DTSTART;TZID=America/New_York:20260302T090000\nRRULE:FREQ=WEEKLY;COUNT=2",
+            "20260101T000000",
+            "20260401T000000",
+        );
+        assert_eq!(starts, vec![utc("20260302T140000"), utc("20260309T130000")]);
+    }
+
+    #[test]
+    fn yearly_weekday_in_a_month() {
+        let starts = expand(
+            "DTSTART;VALUE=DATE:20260112\nRRULE:FREQ=YEARLY;BYMONTH=1;BYDAY=2MO",
+            "20260101T000000",
+            "20290101T000000",
+        );
+        assert_eq!(
+            starts,
+            vec![
+                utc("20260112T000000"),
+                utc("20270111T000000"),
+                utc("20280110T000000")
+            ]
+            .into_iter()
+            .map(|t| t - Duration::hours(8))
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn huge_intervals_end_the_rule() {
+        for frequency in ["DAILY", "WEEKLY", "MONTHLY", "YEARLY"] {
+            let event = format!(
+                "DTSTART:20260105T090000Z\nRRULE:FREQ={};INTERVAL=999999999999999999",
+                frequency
+            );
+            let starts = expand(&event, "20260101T000000", "99991231T000000");
+            assert_eq!(starts, vec![utc("20260105T090000")]);
+        }
+    }
+
+    #[test]
+    fn summaries_are_sanitized() {
+        let events = parse_calendar(&calendar(&[
+            "SUMMARY:\u{1b}[31mstand\u{7}up\\, daily\u{1b}[0m\nDTSTART:20260105T090000Z",
+        ]));
+        assert_eq!(events[0].summary, "standup, daily");
+    }
+}
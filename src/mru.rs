@@ -0,0 +1,173 @@
+use std::cmp::max;
+use std::mem::take;
+
+// most recently used order of the tabs, by position
+#[derive(Debug, Default)]
+pub struct TabHistory {
+    // most recently focused first
+    positions: Vec<usize>,
+}
+
+impl TabHistory {
+    pub fn focus(&mut self, position: usize, tab_count: usize) {
+        self.positions.retain(|p| *p != position && *p < tab_count);
+        self.positions.insert(0, position);
+    }
+
+    // keeps the history pointing at the same tabs when tabs are closed, opened or moved
+    pub fn update_tabs(&mut self, old_names: &[String], new_names: &[String]) {
+        let matches = match_tabs(old_names, new_names);
+        self.positions = self
+            .positions
+            .iter()
+            .filter_map(|p| matches.get(*p).copied().flatten())
+            .collect();
+    }
+
+    // the tab that was focused before the current one
+    pub fn previous(&self) -> Option<usize> {
+        self.positions.get(1).copied()
+    }
+
+    // every tab in mru order, tabs that were never focused come last in position order
+    pub fn order(&self, tab_count: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = self
+            .positions
+            .iter()
+            .copied()
+            .filter(|p| *p < tab_count)
+            .collect();
+        for position in 0..tab_count {
+            if !order.contains(&position) {
+                order.push(position);
+            }
+        }
+        order
+    }
+}
+
+// maps every old tab position to its new position, or None if the tab was closed. tabs don't
+// have ids so they're matched by name in order, then by name out of order for tabs that were
+// moved, and the tabs left between two matches are assumed to have been renamed, unless they have
+// the name zellij gives to new tabs
+fn match_tabs(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old.len()];
+
+    // longest common subsequence of the names
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+
+    // unmatched old and new positions between two matched tabs
+    let mut gaps = vec![];
+    let mut unmatched_old = vec![];
+    let mut unmatched_new = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            gaps.push((take(&mut unmatched_old), take(&mut unmatched_new)));
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            unmatched_old.push(i);
+            i += 1;
+        } else {
+            unmatched_new.push(j);
+            j += 1;
+        }
+    }
+    unmatched_old.extend(i..old.len());
+    unmatched_new.extend(j..new.len());
+    gaps.push((unmatched_old, unmatched_new));
+
+    // moved tabs
+    let mut moved = vec![];
+    for (old_gap, _) in &gaps {
+        for i in old_gap {
+            let new_position = gaps
+                .iter()
+                .flat_map(|(_, new_gap)| new_gap)
+                .find(|j| old[*i] == new[**j] && !moved.iter().any(|(_, m)| m == *j));
+            if let Some(j) = new_position {
+                moved.push((*i, *j));
+            }
+        }
+    }
+    for (i, j) in &moved {
+        matches[*i] = Some(*j);
+    }
+
+    // renamed tabs
+    for (old_gap, new_gap) in gaps {
+        let old_gap: Vec<usize> = old_gap
+            .into_iter()
+            .filter(|i| matches[*i].is_none())
+            .collect();
+        let new_gap = new_gap
+            .iter()
+            .filter(|j| !moved.iter().any(|(_, m)| m == *j) && !is_new_tab_name(&new[**j]));
+        for (i, j) in old_gap.into_iter().zip(new_gap) {
+            matches[i] = Some(*j);
+        }
+    }
+    matches
+}
+
+// `Tab #3`
+fn is_new_tab_name(name: &str) -> bool {
+    name.strip_prefix("Tab #")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn renamed_tab_keeps_its_position() {
+        let matches = match_tabs(&names(&["a", "b", "c"]), &names(&["a", "x", "c"]));
+        assert_eq!(matches, vec![Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn closed_tab_is_dropped() {
+        let matches = match_tabs(&names(&["a", "b", "c"]), &names(&["a", "c"]));
+        assert_eq!(matches, vec![Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn moved_tab_follows_its_name() {
+        let matches = match_tabs(&names(&["a", "b", "c"]), &names(&["b", "c", "a"]));
+        assert_eq!(matches, vec![Some(2), Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn closed_and_opened_tabs_are_not_matched() {
+        let matches = match_tabs(&names(&["a", "b", "c"]), &names(&["a", "b", "Tab #4"]));
+        assert_eq!(matches, vec![Some(0), Some(1), None]);
+        let matches = match_tabs(&names(&["a", "b", "c"]), &names(&["a", "c", "Tab #4"]));
+        assert_eq!(matches, vec![Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn history_follows_the_tabs() {
+        let mut history = TabHistory::default();
+        history.focus(2, 3);
+        history.focus(0, 3);
+        history.update_tabs(&names(&["a", "b", "c"]), &names(&["c", "a"]));
+        assert_eq!(history.order(2), vec![1, 0]);
+        assert_eq!(history.previous(), Some(0));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use zellij_tile::prelude::*;
+
+use crate::system::{render, sparkline, Display, SystemFiles};
+use crate::LinePart;
+
+// how many rates sparklines show, one is added every window
+const HISTORY_SIZE: usize = 8;
+pub const NET_DEV: &str = "proc/net/dev";
+
+#[derive(Debug)]
+struct Sample {
+    at: Instant,
+    // received and transmitted bytes by interface
+    counters: BTreeMap<String, (u64, u64)>,
+}
+
+// bytes per second
+#[derive(Debug, Clone)]
+struct Rate {
+    interface: String,
+    rx: f64,
+    tx: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Network {
+    // the samples of the last window, oldest first
+    samples: VecDeque<Sample>,
+    rate: Option<Rate>,
+    // total rates, most recent last
+    history: VecDeque<f64>,
+    history_pushed_at: Option<Instant>,
+}
+
+impl Network {
+    // rates are averaged over window so they don't jump around with every sample
+    pub fn sample(
+        &mut self,
+        now: Instant,
+        files: &SystemFiles,
+        window: Duration,
+        interface: Option<&str>,
+    ) {
+        let Some(counters) = files.read(NET_DEV).map(parse_net_dev) else {
+            return;
+        };
+        self.samples.push_back(Sample { at: now, counters });
+        // the oldest sample kept is the last one at least window old
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].at) >= window {
+            self.samples.pop_front();
+        }
+        self.rate = self.rate(interface);
+
+        let is_history_due = self
+            .history_pushed_at
+            .is_none_or(|pushed_at| now.duration_since(pushed_at) >= window);
+        if let Some(rate) = self.rate.as_ref().filter(|_| is_history_due) {
+            self.history_pushed_at = Some(now);
+            self.history.push_back(rate.rx + rate.tx);
+            while self.history.len() > HISTORY_SIZE {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    // the rate of interface, or of the busiest one but the loopback
+    fn rate(&self, interface: Option<&str>) -> Option<Rate> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let seconds = last.at.duration_since(first.at).as_secs_f64();
+        if seconds <= 0.0 {
+            return None;
+        }
+        last.counters
+            .iter()
+            .filter(|(name, _)| match interface {
+                Some(interface) => *name == interface,
+                None => *name != "lo",
+            })
+            .filter_map(|(name, (rx, tx))| {
+                let (first_rx, first_tx) = first.counters.get(name)?;
+                // counters go back to 0 when an interface is recreated
+                Some(Rate {
+                    interface: name.clone(),
+                    rx: rx.saturating_sub(*first_rx) as f64 / seconds,
+                    tx: tx.saturating_sub(*first_tx) as f64 / seconds,
+                })
+            })
+            .max_by(|a, b| (a.rx + a.tx).total_cmp(&(b.rx + b.tx)))
+    }
+
+    pub fn render(&self, display: Display, palette: Styling, separator: &str) -> Option<LinePart> {
+        let rate = self.rate.as_ref()?;
+        let rates = format!("↓{} ↑{}", format_rate(rate.rx), format_rate(rate.tx));
+        let text = match display {
+            Display::Text => format!("{} {}", rate.interface, rates),
+            Display::Sparkline => {
+                // the sparkline is relative to the busiest window in it
+                let max = self.history.iter().copied().fold(0.0, f64::max);
+                let history: Vec<f64> = self
+                    .history
+                    .iter()
+                    .map(|r| if max > 0.0 { r / max } else { 0.0 })
+                    .collect();
+                format!("{} {} {}", rate.interface, sparkline(&history), rates)
+            }
+        };
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+}
+
+// two header lines, then `name: rx_bytes rx_packets ... tx_bytes tx_packets ...` per interface
+fn parse_net_dev(net_dev: &str) -> BTreeMap<String, (u64, u64)> {
+    net_dev
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let counters: Vec<u64> = counters
+                .split_whitespace()
+                .map(|c| c.parse().ok())
+                .collect::<Option<_>>()?;
+            Some((
+                name.trim().to_string(),
+                (*counters.first()?, *counters.get(8)?),
+            ))
+        })
+        .collect()
+}
+
+// bytes per second in the largest unit that keeps it above 1, eg. `1.2M`
+fn format_rate(bytes_per_second: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes_per_second;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_dev() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit\n \
+                       face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    \
+                       lo: 1000 10 0 0 0 0 0 0 1000 10 0 0 0 0 0 0\n  \
+                       eth0:123456 100 0 0 0 0 0 0 654321 90 0 0 0 0 0 0\n \
+                       wlan0: 5 1 0 0 0 0 0 0\n";
+        let interfaces = parse_net_dev(net_dev);
+        assert_eq!(interfaces.get("lo"), Some(&(1000, 1000)));
+        assert_eq!(interfaces.get("eth0"), Some(&(123456, 654321)));
+        // lines without the transmit counters are skipped
+        assert_eq!(interfaces.get("wlan0"), None);
+        assert_eq!(interfaces.len(), 2);
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(format_rate(512.0), "512B");
+        assert_eq!(format_rate(1536.0), "1.5K");
+        assert_eq!(format_rate(20.0 * 1024.0 * 1024.0), "20M");
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::line::TIMEZONE;
+use crate::{LinePart, Segment};
+
+// how long each notification is shown before the next queued one
+const ROTATE_AFTER: Duration = Duration::from_secs(3);
+// notifications are dimmed for this long before they expire
+const FADE_OUT: Duration = Duration::from_secs(2);
+// the history and do not disturb mode are kept across plugin reloads
+const DATA_FILE: &str = "/data/notifications.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Level> {
+        match level {
+            "info" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Notification {
+    id: usize,
+    text: String,
+    level: Level,
+    expires_at: Instant,
+}
+
+// notifications received in do not disturb mode, shown once it's turned off
+#[derive(Debug)]
+struct HeldNotification {
+    id: usize,
+    text: String,
+    level: Level,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryEntry {
+    id: usize,
+    // unix timestamp
+    received_at: i64,
+    text: String,
+    level: Level,
+    is_read: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedNotifications {
+    do_not_disturb: bool,
+    history: VecDeque<HistoryEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+    queue: Vec<Notification>,
+    next_id: usize,
+    // index in the queue of the notification being shown
+    shown: usize,
+    shown_since: Option<Instant>,
+    held: Vec<HeldNotification>,
+    do_not_disturb: bool,
+    // most recent last
+    history: VecDeque<HistoryEntry>,
+    history_size: usize,
+}
+
+impl Notifications {
+    pub fn load(history_size: usize) -> Self {
+        let saved: SavedNotifications = fs::read_to_string(DATA_FILE)
+            .ok()
+            .and_then(|saved| serde_json::from_str(&saved).ok())
+            .unwrap_or_default();
+        Notifications {
+            next_id: saved.history.iter().map(|e| e.id + 1).max().unwrap_or(0),
+            do_not_disturb: saved.do_not_disturb,
+            history: saved.history,
+            history_size,
+            ..Default::default()
+        }
+    }
+
+    fn save(&self) {
+        let saved = SavedNotifications {
+            do_not_disturb: self.do_not_disturb,
+            history: self.history.clone(),
+        };
+        let result = serde_json::to_string(&saved)
+            .map_err(|e| e.to_string())
+            .and_then(|saved| fs::write(DATA_FILE, saved).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save notifications: {}", e);
+        }
+    }
+
+    // the oldest entries are dropped when the history is now too long
+    pub fn set_history_size(&mut self, history_size: usize) {
+        self.history_size = history_size;
+        if self.history.len() > history_size {
+            self.history.drain(..self.history.len() - history_size);
+            self.save();
+        }
+    }
+
+    pub fn push(&mut self, text: String, level: Level, ttl: Duration) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.history.push_back(HistoryEntry {
+            id,
+            received_at: Utc::now().timestamp(),
+            text: text.clone(),
+            level,
+            is_read: false,
+        });
+        while self.history.len() > self.history_size {
+            self.history.pop_front();
+        }
+        self.save();
+        if self.do_not_disturb {
+            self.held.push(HeldNotification {
+                id,
+                text,
+                level,
+                ttl,
+            });
+        } else {
+            self.show(id, text, level, ttl);
+        }
+    }
+
+    fn show(&mut self, id: usize, text: String, level: Level, ttl: Duration) {
+        let now = Instant::now();
+        self.queue.push(Notification {
+            id,
+            text,
+            level,
+            expires_at: now + ttl,
+        });
+        if self.shown_since.is_none() {
+            self.shown_since = Some(now);
+        }
+    }
+
+    pub fn dismiss(&mut self, id: usize) {
+        if let Some(index) = self.queue.iter().position(|n| n.id == id) {
+            self.queue.remove(index);
+            self.show_next(index, Instant::now());
+        }
+        if let Some(entry) = self.history.iter_mut().find(|e| e.id == id) {
+            entry.is_read = true;
+            self.save();
+        }
+    }
+
+    pub fn is_do_not_disturb(&self) -> bool {
+        self.do_not_disturb
+    }
+
+    pub fn set_do_not_disturb(&mut self, do_not_disturb: bool) {
+        self.do_not_disturb = do_not_disturb;
+        if !do_not_disturb {
+            for held in std::mem::take(&mut self.held) {
+                self.show(held.id, held.text, held.level, held.ttl);
+            }
+        }
+        self.save();
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for entry in self.history.iter_mut() {
+            entry.is_read = true;
+        }
+        self.save();
+    }
+
+    fn unread_count(&self) -> usize {
+        self.history.iter().filter(|e| !e.is_read).count()
+    }
+
+    // one line per notification, oldest first
+    pub fn history_text(&self) -> String {
+        self.history
+            .iter()
+            .map(|entry| {
+                let received_at = Utc
+                    .timestamp_opt(entry.received_at, 0)
+                    .single()
+                    .map(|t| t.with_timezone(&TIMEZONE).format("%Y-%m-%d %H:%M:%S"))
+                    .map(|t| t.to_string())
+                    .unwrap_or_default();
+                format!(
+                    "{} {} {:?} {}",
+                    if entry.is_read { " " } else { "*" },
+                    received_at,
+                    entry.level,
+                    entry.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // drops expired notifications and rotates through the queued ones
+    pub fn tick(&mut self, now: Instant) {
+        if self.queue.is_empty() {
+            return;
+        }
+        let shown_id = self.queue.get(self.shown).map(|n| n.id);
+        self.queue.retain(|n| n.expires_at > now);
+        match self.queue.iter().position(|n| Some(n.id) == shown_id) {
+            Some(index) => {
+                self.shown = index;
+                let shown_since = self.shown_since.get_or_insert(now);
+                if now.duration_since(*shown_since) >= ROTATE_AFTER {
+                    self.show_next(index + 1, now);
+                }
+            }
+            None => self.show_next(self.shown, now),
+        }
+    }
+
+    fn show_next(&mut self, index: usize, now: Instant) {
+        self.shown = if index < self.queue.len() { index } else { 0 };
+        self.shown_since = if self.queue.is_empty() {
+            None
+        } else {
+            Some(now)
+        };
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let notification = self.queue.get(self.shown)?;
+        let text = if self.queue.len() > 1 {
+            format!(" {} +{} ", notification.text, self.queue.len() - 1)
+        } else {
+            format!(" {} ", notification.text)
+        };
+        let part = format!("{}{}", separator, text);
+        let len = part.width();
+
+        let fg = palette.text_unselected.background;
+        let bg = match notification.level {
+            Level::Info => palette.ribbon_selected.background,
+            Level::Warn => palette.text_unselected.emphasis_0,
+            Level::Error => palette.exit_code_error.base,
+        };
+        let is_fading = notification
+            .expires_at
+            .saturating_duration_since(Instant::now())
+            < FADE_OUT;
+        let part = if is_fading {
+            style!(fg, bg).dimmed().paint(part).to_string()
+        } else {
+            style!(fg, bg).bold().paint(part).to_string()
+        };
+
+        Some(LinePart {
+            part,
+            len,
+            tab_index: None,
+            segment: Some(Segment::Notification(notification.id)),
+        })
+    }
+
+    // the unread count, and whether do not disturb mode is on
+    pub fn render_badge(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let unread_count = self.unread_count();
+        let text = match (self.do_not_disturb, unread_count) {
+            (false, 0) => return None,
+            (false, unread_count) => format!(" ✉ {} ", unread_count),
+            (true, 0) => " DND ".to_string(),
+            (true, unread_count) => format!(" DND ✉ {} ", unread_count),
+        };
+        let part = format!("{}{}", separator, text);
+        let len = part.width();
+
+        let fg = palette.text_unselected.background;
+        let bg = palette.ribbon_unselected.background;
+        let part = style!(fg, bg).bold().paint(part).to_string();
+
+        Some(LinePart {
+            part,
+            len,
+            tab_index: None,
+            segment: Some(Segment::NotificationBadge),
+        })
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use zellij_tile::prelude::*;
+
+use crate::command::truncate;
+use crate::line::strip_ansi;
+use crate::notification::Level;
+use crate::pomodoro::PomodoroAction;
+use crate::timer::{parse_duration, TimerAction, TimerSelector};
+
+// messages for the plugin are named `compact-bar::<command>`, with their arguments passed either
+// as pipe args or as `key=value` pairs in the payload, eg.
+// `zellij pipe --name compact-bar::focus-tab --args name=^web`
+const PIPE_NAME_PREFIX: &str = "compact-bar::";
+
+const DEFAULT_NOTIFICATION_TTL: Duration = Duration::from_secs(5);
+// labels are cut to this many columns
+const MAX_LABEL_WIDTH: usize = 30;
+
+pub enum PipeCommand {
+    LastTab,
+    FocusTab(Regex),
+    FocusOrCreate(String),
+    SetConfig(BTreeMap<String, String>),
+    Toggle(String),
+    Reload,
+    // the state of the bar as json, or the bar itself as plain text
+    State {
+        as_text: bool,
+    },
+    Notify {
+        text: String,
+        level: Level,
+        ttl: Duration,
+    },
+    // turns do not disturb mode on or off, toggles it if None
+    DoNotDisturb(Option<bool>),
+    // prints the received notifications and marks them as read
+    History,
+    Progress {
+        id: String,
+        value: f64,
+        label: Option<String>,
+    },
+    Pomodoro(PomodoroAction),
+    Timer(TimerAction),
+}
+
+impl PipeCommand {
+    // None if the message isn't meant for this plugin
+    pub fn parse(pipe_message: &PipeMessage) -> Option<Result<PipeCommand, String>> {
+        let command = pipe_message.name.strip_prefix(PIPE_NAME_PREFIX)?;
+        let args = pipe_args(pipe_message);
+        let command = match command {
+            "last-tab" => Ok(PipeCommand::LastTab),
+            "focus-tab" => required_arg(&args, "name").and_then(|name| {
+                Regex::new(name)
+                    .map(PipeCommand::FocusTab)
+                    .map_err(|e| format!("invalid name pattern: {}", e))
+            }),
+            "focus-or-create" => {
+                required_arg(&args, "name").map(|name| PipeCommand::FocusOrCreate(name.clone()))
+            }
+            "set-config" if args.is_empty() => Err("expected key=value arguments".to_string()),
+            "set-config" => Ok(PipeCommand::SetConfig(args)),
+            "toggle" => required_arg(&args, "segment").map(|id| PipeCommand::Toggle(id.clone())),
+            "reload" => Ok(PipeCommand::Reload),
+            "state" => match args.get("format").map(|f| f.as_str()) {
+                None | Some("json") => Ok(PipeCommand::State { as_text: false }),
+                Some("text") => Ok(PipeCommand::State { as_text: true }),
+                Some(format) => Err(format!("unknown format: {}", format)),
+            },
+            "notify" => parse_notification(pipe_message, &args),
+            "dnd" => match args.get("state").map(|s| s.as_str()) {
+                None | Some("toggle") => Ok(PipeCommand::DoNotDisturb(None)),
+                Some("on") => Ok(PipeCommand::DoNotDisturb(Some(true))),
+                Some("off") => Ok(PipeCommand::DoNotDisturb(Some(false))),
+                Some(state) => Err(format!("unknown state: {}", state)),
+            },
+            "history" => Ok(PipeCommand::History),
+            "progress" => required_arg(&args, "value").and_then(|value| {
+                let value = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .ok_or_else(|| format!("invalid value: {}", value))?;
+                Ok(PipeCommand::Progress {
+                    id: args.get("id").cloned().unwrap_or_default(),
+                    value,
+                    label: label(&args),
+                })
+            }),
+            "pomodoro" => match args.get("action") {
+                None => Ok(PipeCommand::Pomodoro(PomodoroAction::Toggle)),
+                Some(action) => PomodoroAction::parse(action)
+                    .map(PipeCommand::Pomodoro)
+                    .ok_or_else(|| format!("unknown action: {}", action)),
+            },
+            "timer" => parse_timer(pipe_message, &args),
+            _ => Err(format!("unknown command: {}", command)),
+        };
+        Some(command)
+    }
+}
+
+// the text is either the `text` argument or the rest of the payload, eg.
+// `zellij pipe --name compact-bar::notify --args level=error,ttl=30 -- "build failed"`
+fn parse_notification(
+    pipe_message: &PipeMessage,
+    args: &BTreeMap<String, String>,
+) -> Result<PipeCommand, String> {
+    let text = match args.get("text") {
+        Some(text) => text.clone(),
+        None => pipe_message
+            .payload
+            .iter()
+            .flat_map(|payload| payload.split_whitespace())
+            .filter(|word| !word.contains('='))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    // escape sequences and control characters would break the bar and the length of the text
+    let text = strip_ansi(&text).trim().to_string();
+    if text.is_empty() {
+        return Err("missing notification text".to_string());
+    }
+    let level = match args.get("level") {
+        Some(level) => Level::parse(level).ok_or_else(|| format!("unknown level: {}", level))?,
+        None => Level::Info,
+    };
+    let ttl = match args.get("ttl") {
+        Some(ttl) => ttl
+            .parse::<f64>()
+            .ok()
+            .filter(|ttl| ttl.is_finite() && *ttl > 0.0)
+            .and_then(|ttl| Duration::try_from_secs_f64(ttl).ok())
+            // the notification expires at now + ttl
+            .filter(|ttl| Instant::now().checked_add(*ttl).is_some())
+            .ok_or_else(|| format!("invalid ttl: {}", ttl))?,
+        None => DEFAULT_NOTIFICATION_TTL,
+    };
+    Ok(PipeCommand::Notify { text, level, ttl })
+}
+
+// the action and the length can also be the first words of the payload, eg.
+// `zellij pipe --name compact-bar::timer -- start 4m label=tea`
+fn parse_timer(
+    pipe_message: &PipeMessage,
+    args: &BTreeMap<String, String>,
+) -> Result<PipeCommand, String> {
+    let mut words = pipe_message
+        .payload
+        .iter()
+        .flat_map(|payload| payload.split_whitespace())
+        .filter(|word| !word.contains('='));
+    let action = args
+        .get("action")
+        .map(|a| a.as_str())
+        .or_else(|| words.next())
+        .unwrap_or("start");
+    let selector = || -> Result<TimerSelector, String> {
+        let id = match args.get("id") {
+            Some(id) => Some(id.parse().map_err(|_| format!("invalid id: {}", id))?),
+            None => None,
+        };
+        Ok(TimerSelector {
+            id,
+            label: label(args),
+        })
+    };
+    let action = match action {
+        "start" => {
+            let length = match args
+                .get("duration")
+                .map(|d| d.as_str())
+                .or_else(|| words.next())
+            {
+                Some(length) => Some(
+                    parse_duration(length)
+                        .ok_or_else(|| format!("invalid duration: {}", length))?,
+                ),
+                None => None,
+            };
+            TimerAction::Start {
+                length,
+                label: label(args),
+            }
+        }
+        "stop" => TimerAction::Stop(selector()?),
+        "pause" => TimerAction::Pause(selector()?),
+        "list" => TimerAction::List,
+        action => return Err(format!("unknown action: {}", action)),
+    };
+    Ok(PipeCommand::Timer(action))
+}
+
+// labels are shown in the bar, where escape sequences and control characters would break it and
+// its length
+fn label(args: &BTreeMap<String, String>) -> Option<String> {
+    let label = strip_ansi(args.get("label")?);
+    Some(truncate(label.trim(), MAX_LABEL_WIDTH)).filter(|label| !label.is_empty())
+}
+
+fn pipe_args(pipe_message: &PipeMessage) -> BTreeMap<String, String> {
+    let mut args = pipe_message.args.clone();
+    if let Some(payload) = &pipe_message.payload {
+        for arg in payload.split_whitespace() {
+            if let Some((key, value)) = arg.split_once('=') {
+                args.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    args
+}
+
+fn required_arg<'a>(args: &'a BTreeMap<String, String>, key: &str) -> Result<&'a String, String> {
+    args.get(key)
+        .ok_or_else(|| format!("missing argument: {}=<value>", key))
+}
+
+// results are only written back to pipes coming from the cli, keybindings have nowhere to show
+// them
+pub fn reply(pipe_message: &PipeMessage, result: Result<String, String>) {
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Failed to run {}: {}", pipe_message.name, e);
+            format!("error: {}", e)
+        }
+    };
+    if let PipeSource::Cli(pipe_id) = &pipe_message.source {
+        cli_pipe_output(pipe_id, &format!("{}\n", output));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(name: &str, payload: &str) -> PipeMessage {
+        PipeMessage {
+            source: PipeSource::Keybind,
+            name: format!("{}{}", PIPE_NAME_PREFIX, name),
+            payload: Some(payload.to_string()),
+            args: BTreeMap::new(),
+            is_private: false,
+        }
+    }
+
+    #[test]
+    fn notification_text_is_stripped() {
+        let command =
+            PipeCommand::parse(&message("notify", "\u{1b}[31mbuild\u{7} failed\u{1b}[0m"));
+        match command {
+            Some(Ok(PipeCommand::Notify { text, .. })) => assert_eq!(text, "build failed"),
+            _ => panic!("expected a notification"),
+        }
+    }
+
+    #[test]
+    fn progress_labels_are_sanitized() {
+        let command =
+            PipeCommand::parse(&message("progress", "value=0.5 label=\u{1b}[31mbuild\u{7}"));
+        assert!(matches!(
+            command,
+            Some(Ok(PipeCommand::Progress { label: Some(label), .. })) if label == "build"
+        ));
+    }
+
+    #[test]
+    fn timer_labels_are_sanitized() {
+        let command = PipeCommand::parse(&message("timer", "start 5m label=\u{1b}[1mtea\u{7}"));
+        assert!(matches!(
+            command,
+            Some(Ok(PipeCommand::Timer(TimerAction::Start { label: Some(label), .. })))
+                if label == "tea"
+        ));
+        let command = PipeCommand::parse(&message("timer", "stop label=\u{1b}[1mtea"));
+        assert!(matches!(
+            command,
+            Some(Ok(PipeCommand::Timer(TimerAction::Stop(TimerSelector { label: Some(label), .. }))))
+                if label == "tea"
+        ));
+    }
+
+    #[test]
+    fn huge_ttl_is_rejected() {
+        let command = PipeCommand::parse(&message("notify", "ttl=1e30 done"));
+        assert!(matches!(command, Some(Err(_))));
+        // fits in a duration but not in an instant
+        let command = PipeCommand::parse(&message("notify", "ttl=1e19 done"));
+        assert!(matches!(command, Some(Err(e)) if e == "invalid ttl: 1e19"));
+    }
+}
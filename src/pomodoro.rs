@@ -0,0 +1,333 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::{LinePart, Segment};
+
+// the phase is kept across plugin reloads
+const DATA_FILE: &str = "/data/pomodoro.json";
+// the segment flashes for this long when a phase ends
+const FLASH_FOR: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PomodoroAction {
+    Start,
+    Pause,
+    Toggle,
+    Reset,
+    Skip,
+    Status,
+}
+
+impl PomodoroAction {
+    pub fn parse(action: &str) -> Option<PomodoroAction> {
+        match action {
+            "start" => Some(PomodoroAction::Start),
+            "pause" => Some(PomodoroAction::Pause),
+            "toggle" => Some(PomodoroAction::Toggle),
+            "reset" => Some(PomodoroAction::Reset),
+            "skip" => Some(PomodoroAction::Skip),
+            "status" => Some(PomodoroAction::Status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Timer {
+    // not started, or reset
+    #[default]
+    Idle,
+    // unix timestamp in milliseconds, so it survives reloads
+    Running {
+        ends_at: i64,
+    },
+    // milliseconds left
+    Paused {
+        remaining: i64,
+    },
+}
+
+// phase lengths
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PomodoroConfig {
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    // every this many work phases, the break is a long one
+    pub long_break_every: usize,
+}
+
+impl PomodoroConfig {
+    fn length(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        }
+    }
+
+    // in milliseconds, phases too long for the timestamps never end
+    fn length_millis(&self, phase: Phase) -> i64 {
+        i64::try_from(self.length(phase).as_millis()).unwrap_or(i64::MAX)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedPomodoro {
+    phase: Phase,
+    // work phases done since the last reset
+    completed: usize,
+    timer: Timer,
+}
+
+#[derive(Debug, Default)]
+pub struct Pomodoro {
+    saved: SavedPomodoro,
+    flash_since: Option<Instant>,
+}
+
+impl Pomodoro {
+    pub fn load() -> Self {
+        let saved = fs::read_to_string(DATA_FILE)
+            .ok()
+            .and_then(|saved| serde_json::from_str(&saved).ok())
+            .unwrap_or_default();
+        Pomodoro {
+            saved,
+            flash_since: None,
+        }
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_string(&self.saved)
+            .map_err(|e| e.to_string())
+            .and_then(|saved| fs::write(DATA_FILE, saved).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save the pomodoro: {}", e);
+        }
+    }
+
+    pub fn start(&mut self, config: &PomodoroConfig) {
+        let remaining = match self.saved.timer {
+            Timer::Running { .. } => return,
+            Timer::Paused { remaining } => remaining,
+            Timer::Idle => config.length_millis(self.saved.phase),
+        };
+        self.saved.timer = Timer::Running {
+            ends_at: Utc::now().timestamp_millis().saturating_add(remaining),
+        };
+        self.save();
+    }
+
+    pub fn pause(&mut self) {
+        if let Timer::Running { ends_at } = self.saved.timer {
+            let remaining = (ends_at - Utc::now().timestamp_millis()).max(0);
+            self.saved.timer = Timer::Paused { remaining };
+            self.save();
+        }
+    }
+
+    pub fn toggle(&mut self, config: &PomodoroConfig) {
+        if matches!(self.saved.timer, Timer::Running { .. }) {
+            self.pause();
+        } else {
+            self.start(config);
+        }
+    }
+
+    // back to the first work phase, not started
+    pub fn reset(&mut self) {
+        self.saved = SavedPomodoro::default();
+        self.flash_since = None;
+        self.save();
+    }
+
+    // goes to the next phase right away, keeping it running or not
+    pub fn skip(&mut self, config: &PomodoroConfig) {
+        let now = Utc::now().timestamp_millis();
+        let next = self.next_phase(config);
+        self.saved.timer = match self.saved.timer {
+            Timer::Running { .. } => Timer::Running {
+                ends_at: now.saturating_add(config.length_millis(next)),
+            },
+            _ => Timer::Idle,
+        };
+        self.save();
+    }
+
+    // moves on to the next phase, which starts right away, when the current one is over
+    pub fn tick(&mut self, now: Instant, config: &PomodoroConfig) {
+        let Timer::Running { ends_at } = self.saved.timer else {
+            return;
+        };
+        let wall_now = Utc::now().timestamp_millis();
+        if ends_at > wall_now {
+            return;
+        }
+        let ends_at = self.catch_up(ends_at, wall_now, config);
+        self.saved.timer = Timer::Running { ends_at };
+        self.flash_since = Some(now);
+        self.save();
+    }
+
+    // goes through the phases that ended by wall_now, including the ones that ended while the
+    // plugin wasn't loaded, and returns when the current one ends
+    fn catch_up(&mut self, mut ends_at: i64, wall_now: i64, config: &PomodoroConfig) -> i64 {
+        while ends_at <= wall_now {
+            let next = self.next_phase(config);
+            // phases shorter than a millisecond still move the end forward
+            ends_at = ends_at.saturating_add(config.length_millis(next).max(1));
+        }
+        ends_at
+    }
+
+    fn next_phase(&mut self, config: &PomodoroConfig) -> Phase {
+        self.saved.phase = match self.saved.phase {
+            Phase::Work => {
+                self.saved.completed += 1;
+                if config.long_break_every > 0
+                    && self.saved.completed.is_multiple_of(config.long_break_every)
+                {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+        self.saved.phase
+    }
+
+    // the phase and what's left of it, in text
+    pub fn status(&self, config: &PomodoroConfig) -> String {
+        let phase = match self.saved.phase {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short break",
+            Phase::LongBreak => "long break",
+        };
+        let state = match self.saved.timer {
+            Timer::Idle => "stopped",
+            Timer::Running { .. } => "running",
+            Timer::Paused { .. } => "paused",
+        };
+        format!(
+            "{} {} {}",
+            phase,
+            format_remaining(self.remaining(config)),
+            state
+        )
+    }
+
+    fn remaining(&self, config: &PomodoroConfig) -> Duration {
+        let millis = match self.saved.timer {
+            Timer::Idle => return config.length(self.saved.phase),
+            Timer::Running { ends_at } => ends_at - Utc::now().timestamp_millis(),
+            Timer::Paused { remaining } => remaining,
+        };
+        Duration::from_millis(millis.max(0) as u64)
+    }
+
+    pub fn render(&self, config: &PomodoroConfig, palette: Styling, separator: &str) -> LinePart {
+        let icon = match self.saved.phase {
+            Phase::Work => "🍅",
+            Phase::ShortBreak | Phase::LongBreak => "☕",
+        };
+        let mut text = format!(" {} {}", icon, format_remaining(self.remaining(config)));
+        if matches!(self.saved.timer, Timer::Paused { .. }) {
+            text.push_str(" ⏸");
+        }
+        let part = format!("{}{} ", separator, text);
+        let len = part.width();
+
+        let fg = palette.text_unselected.background;
+        let bg = match self.saved.phase {
+            Phase::Work => palette.ribbon_unselected.background,
+            Phase::ShortBreak | Phase::LongBreak => palette.ribbon_selected.background,
+        };
+        // the colors are swapped every other tick while flashing
+        let is_flashing = self.flash_since.is_some_and(|since| {
+            let elapsed = since.elapsed();
+            elapsed < FLASH_FOR && elapsed.as_millis() / 500 % 2 == 0
+        });
+        let part = if is_flashing {
+            style!(bg, fg).bold().paint(part).to_string()
+        } else {
+            style!(fg, bg).bold().paint(part).to_string()
+        };
+
+        LinePart {
+            part,
+            len,
+            tab_index: None,
+            segment: Some(Segment::Pomodoro),
+        }
+    }
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn config() -> PomodoroConfig {
+        PomodoroConfig {
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            long_break_every: 2,
+        }
+    }
+
+    #[test]
+    fn ended_phase_starts_the_next_one() {
+        let mut pomodoro = Pomodoro::default();
+        let ends_at = pomodoro.catch_up(0, 1, &config());
+        assert_eq!(pomodoro.saved.phase, Phase::ShortBreak);
+        assert_eq!(pomodoro.saved.completed, 1);
+        assert_eq!(ends_at, 5 * MINUTE);
+    }
+
+    #[test]
+    fn phases_missed_while_unloaded_are_skipped() {
+        let mut pomodoro = Pomodoro::default();
+        // work ended 31 minutes ago, the short break and the next work phase are over too
+        let ends_at = pomodoro.catch_up(0, 31 * MINUTE, &config());
+        assert_eq!(pomodoro.saved.phase, Phase::LongBreak);
+        assert_eq!(pomodoro.saved.completed, 2);
+        assert_eq!(ends_at, 45 * MINUTE);
+    }
+
+    #[test]
+    fn huge_phases_never_end() {
+        let mut pomodoro = Pomodoro::default();
+        let config = PomodoroConfig {
+            short_break: Duration::MAX,
+            ..config()
+        };
+        assert_eq!(pomodoro.catch_up(0, 1, &config), i64::MAX);
+        assert_eq!(pomodoro.saved.phase, Phase::ShortBreak);
+    }
+}
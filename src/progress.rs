@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::LinePart;
+
+const BAR_WIDTH: usize = 8;
+// partially filled cells, in eighths
+const PARTIAL_BLOCKS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+// completed items stay at 100% for a moment before they're removed
+const DONE_LINGER: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+struct ProgressItem {
+    id: String,
+    // 0 to 100
+    value: f64,
+    label: Option<String>,
+    updated_at: Instant,
+    // every item gets the next color, so items next to each other are told apart
+    color_index: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Progress {
+    items: Vec<ProgressItem>,
+    next_color_index: usize,
+}
+
+impl Progress {
+    pub fn update(&mut self, id: String, value: f64, label: Option<String>) {
+        let value = value.clamp(0.0, 100.0);
+        let updated_at = Instant::now();
+        match self.items.iter_mut().find(|i| i.id == id) {
+            Some(item) => {
+                item.value = value;
+                item.updated_at = updated_at;
+                if label.is_some() {
+                    item.label = label;
+                }
+            }
+            None => {
+                self.items.push(ProgressItem {
+                    id,
+                    value,
+                    label,
+                    updated_at,
+                    color_index: self.next_color_index,
+                });
+                self.next_color_index += 1;
+            }
+        }
+    }
+
+    // removes completed items and the ones that weren't updated for longer than timeout
+    pub fn tick(&mut self, now: Instant, timeout: Duration) {
+        self.items.retain(|item| {
+            let since_update = now.duration_since(item.updated_at);
+            if item.value >= 100.0 {
+                since_update < DONE_LINGER
+            } else {
+                since_update < timeout
+            }
+        });
+    }
+
+    pub fn render(&self, palette: Styling, separator: &str) -> Vec<LinePart> {
+        let colors = [
+            palette.text_unselected.emphasis_1,
+            palette.text_unselected.emphasis_0,
+            palette.text_unselected.emphasis_3,
+            palette.text_unselected.emphasis_2,
+        ];
+        let text_color = palette.text_unselected.base;
+        let bg = palette.text_unselected.background;
+        let track_bg = palette.ribbon_unselected.background;
+        self.items
+            .iter()
+            .map(|item| {
+                let label = match &item.label {
+                    Some(label) => format!("{}{} ", separator, label),
+                    None => separator.to_string(),
+                };
+                let bar = progress_bar(item.value, BAR_WIDTH);
+                let percent = format!(" {:.0}% ", item.value);
+                let len = label.width() + BAR_WIDTH + percent.width();
+
+                let color = colors[item.color_index % colors.len()];
+                let part = format!(
+                    "{}{}{}",
+                    style!(text_color, bg).paint(label),
+                    style!(color, track_bg).paint(bar),
+                    style!(text_color, bg).bold().paint(percent),
+                );
+                LinePart {
+                    part,
+                    len,
+                    tab_index: None,
+                    segment: None,
+                }
+            })
+            .collect()
+    }
+}
+
+// value is from 0 to 100, the bar is always width cells wide
+fn progress_bar(value: f64, width: usize) -> String {
+    let eighths = (value / 100.0 * (width * 8) as f64).round() as usize;
+    let full_cells = eighths / 8;
+    let mut bar = "█".repeat(full_cells);
+    if full_cells < width {
+        bar.push(PARTIAL_BLOCKS[eighths % 8]);
+        bar.push_str(&" ".repeat(width - full_cells - 1));
+    }
+    bar
+}
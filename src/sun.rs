@@ -0,0 +1,152 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use zellij_tile::prelude::*;
+
+use crate::line::TIMEZONE;
+use crate::system::render;
+use crate::LinePart;
+
+// julian day of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+// julian day of the unix epoch
+const UNIX_EPOCH: f64 = 2440587.5;
+// the sun is below the horizon when its center is this far below it, because of refraction and
+// its size
+const HORIZON: f64 = -0.833;
+// a new moon, and the mean length of a lunar month
+const NEW_MOON: i64 = 947182440;
+const SYNODIC_MONTH: f64 = 29.530588853;
+const MOON_PHASES: [&str; 8] = ["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunEvent {
+    Sunrise,
+    Sunset,
+}
+
+// latitude and longitude in degrees, north and east are positive
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Location {
+    // the sunrise and sunset of the day, or whether the sun stays up all day near the poles, from
+    // the sunrise equation
+    fn sun_times(&self, date: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>), bool> {
+        let days = (date - NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default()).num_days();
+        let mean_solar_time = days as f64 + 0.0008 - self.longitude / 360.0;
+        let anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+        let m = anomaly.to_radians();
+        let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+        let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+        let l = ecliptic_longitude.to_radians();
+        let transit = J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * l).sin();
+        let declination = (l.sin() * 23.4397_f64.to_radians().sin()).asin();
+        let latitude = self.latitude.to_radians();
+        let cos_hour_angle = (HORIZON.to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return Err(cos_hour_angle < -1.0);
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        Ok((
+            from_julian_day(transit - hour_angle / 360.0),
+            from_julian_day(transit + hour_angle / 360.0),
+        ))
+    }
+
+    // the next sunrise or sunset in the coming days, none during a polar day or night
+    pub fn next_event(&self, now: DateTime<Utc>) -> Option<(SunEvent, DateTime<Utc>)> {
+        let today = now.with_timezone(&TIMEZONE).date_naive();
+        (-1..=2)
+            .filter_map(|offset| self.sun_times(today + Duration::days(offset)).ok())
+            .flat_map(|(sunrise, sunset)| {
+                [(SunEvent::Sunrise, sunrise), (SunEvent::Sunset, sunset)]
+            })
+            .find(|(_, at)| *at > now)
+    }
+
+    pub fn is_night(&self, now: DateTime<Utc>) -> bool {
+        match self.next_event(now) {
+            Some((event, _)) => event == SunEvent::Sunrise,
+            None => {
+                let today = now.with_timezone(&TIMEZONE).date_naive();
+                self.sun_times(today) == Err(false)
+            }
+        }
+    }
+
+    pub fn render(
+        &self,
+        now: DateTime<Utc>,
+        moon_phase: bool,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let mut text = match self.next_event(now) {
+            Some((SunEvent::Sunrise, at)) => {
+                format!("🌅 {}", at.with_timezone(&TIMEZONE).format("%H:%M"))
+            }
+            Some((SunEvent::Sunset, at)) => {
+                format!("🌇 {}", at.with_timezone(&TIMEZONE).format("%H:%M"))
+            }
+            None if self.is_night(now) => "🌃".to_string(),
+            None => "☀".to_string(),
+        };
+        if moon_phase {
+            text.push(' ');
+            text.push_str(moon_phase_glyph(now));
+        }
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+}
+
+// the colors replacing those of the theme at night, in the slots used by the tabs, the clock and
+// the other segments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NightColors {
+    pub background: PaletteColor,
+    pub ribbon_selected: PaletteColor,
+    pub ribbon_unselected: PaletteColor,
+    pub text: PaletteColor,
+}
+
+impl Default for NightColors {
+    fn default() -> Self {
+        NightColors {
+            background: PaletteColor::Rgb((28, 28, 40)),
+            ribbon_selected: PaletteColor::Rgb((95, 95, 135)),
+            ribbon_unselected: PaletteColor::Rgb((58, 58, 78)),
+            text: PaletteColor::Rgb((198, 198, 208)),
+        }
+    }
+}
+
+impl NightColors {
+    pub fn apply(&self, mut palette: Styling) -> Styling {
+        palette.text_unselected.background = self.background;
+        palette.ribbon_selected.background = self.ribbon_selected;
+        palette.ribbon_unselected.background = self.ribbon_unselected;
+        palette.ribbon_selected.base = self.text;
+        palette.ribbon_unselected.base = self.text;
+        palette
+    }
+}
+
+// from the time since a known new moon, the orbit is regular enough for a glyph
+fn moon_phase_glyph(now: DateTime<Utc>) -> &'static str {
+    let days = (now.timestamp() - NEW_MOON) as f64 / 86400.0;
+    let age = days.rem_euclid(SYNODIC_MONTH) / SYNODIC_MONTH;
+    MOON_PHASES[(age * 8.0).round() as usize % 8]
+}
+
+fn from_julian_day(day: f64) -> DateTime<Utc> {
+    let seconds = ((day - UNIX_EPOCH) * 86400.0).round() as i64;
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
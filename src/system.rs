@@ -0,0 +1,466 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::command::shell_quote;
+use crate::LinePart;
+
+// key of the run_command context of the runs printing /proc and /sys
+pub const SYSTEM_CONTEXT_KEY: &str = "system_files";
+// printed before the path of every file
+const FILE_SEPARATOR: char = '\u{1e}';
+// the files read by the cpu, memory, load and uptime segments
+pub const PROC_FILES: [&str; 4] = ["proc/stat", "proc/meminfo", "proc/loadavg", "proc/uptime"];
+
+// how many samples sparklines show
+const HISTORY_SIZE: usize = 8;
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// values at which a segment turns orange and red, higher values are worse
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub warn: f64,
+    pub critical: f64,
+}
+
+impl Thresholds {
+    pub fn color(&self, value: f64, palette: Styling) -> PaletteColor {
+        if value >= self.critical {
+            palette.exit_code_error.base
+        } else if value >= self.warn {
+            palette.text_unselected.emphasis_0
+        } else {
+            palette.ribbon_unselected.background
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Display {
+    #[default]
+    Text,
+    Sparkline,
+}
+
+impl Display {
+    pub fn parse(display: &str) -> Option<Display> {
+        match display {
+            "text" | "true" => Some(Display::Text),
+            "sparkline" => Some(Display::Sparkline),
+            _ => None,
+        }
+    }
+}
+
+// time spent by all cpus since boot, in clock ticks
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Memory {
+    // in kB
+    used: u64,
+    total: u64,
+}
+
+// the /proc and /sys files the segments read. They are printed by cat, reading them directly needs
+// the host folder to be /, and zellij would then watch the whole filesystem for changes
+#[derive(Debug, Default)]
+pub struct SystemFiles {
+    // contents by path relative to the root, eg. `proc/stat`
+    files: BTreeMap<String, String>,
+    // cat is never started again before its last run is done
+    is_running: bool,
+    last_run_at: Option<Instant>,
+}
+
+impl SystemFiles {
+    // prints the files matching the shell patterns under root if the last run is older than
+    // interval, files that don't exist are left out
+    pub fn tick(&mut self, now: Instant, interval: Duration, root: &Path, patterns: &[&str]) {
+        if self.is_running || patterns.is_empty() || !is_due(&mut self.last_run_at, now, interval) {
+            return;
+        }
+        self.is_running = true;
+
+        let script = format!(
+            "cd {} || exit; for f in {}; do [ -f \"$f\" ] && printf '\\036%s\\n' \"$f\" && cat \"$f\"; done; true",
+            shell_quote(&root.to_string_lossy()),
+            patterns.join(" ")
+        );
+        let mut context = BTreeMap::new();
+        context.insert(SYSTEM_CONTEXT_KEY.to_string(), String::new());
+        run_command(&["sh", "-c", &script], context);
+    }
+
+    pub fn handle_result(&mut self, exit_code: Option<i32>, stdout: &[u8], stderr: &[u8]) {
+        self.is_running = false;
+        if exit_code != Some(0) {
+            eprintln!(
+                "Failed to read /proc and /sys: {}",
+                String::from_utf8_lossy(stderr).trim()
+            );
+            return;
+        }
+        self.files = parse_files(&String::from_utf8_lossy(stdout));
+    }
+
+    pub fn read(&self, path: &str) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    // the names of the folders in folder that have files, eg. `thermal_zone0` in
+    // `sys/class/thermal`
+    pub fn folders(&self, folder: &str) -> Vec<&str> {
+        let prefix = format!("{}/", folder);
+        let mut folders: Vec<&str> = self
+            .files
+            .keys()
+            .filter_map(|path| path.strip_prefix(&prefix)?.split_once('/'))
+            .map(|(name, _)| name)
+            .collect();
+        folders.dedup();
+        folders
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SystemStats {
+    cpu_times: Option<CpuTimes>,
+    cpu_count: usize,
+    // the values of the last samples from 0 to 1, most recent last
+    cpu: VecDeque<f64>,
+    memory: Option<Memory>,
+    memory_history: VecDeque<f64>,
+    load_average: Option<[f64; 3]>,
+    load_history: VecDeque<f64>,
+    uptime: Option<Duration>,
+}
+
+impl SystemStats {
+    pub fn sample(&mut self, files: &SystemFiles) {
+        if let Some((cpu_times, cpu_count)) = files.read("proc/stat").and_then(parse_stat) {
+            // usage is the share of busy time since the previous sample
+            if let Some(previous) = self.cpu_times {
+                let total = cpu_times.total.saturating_sub(previous.total);
+                if total > 0 {
+                    let busy = cpu_times.busy.saturating_sub(previous.busy);
+                    push_sample(&mut self.cpu, busy as f64 / total as f64);
+                }
+            }
+            self.cpu_times = Some(cpu_times);
+            self.cpu_count = cpu_count;
+        }
+        self.memory = files.read("proc/meminfo").and_then(parse_meminfo);
+        if let Some(memory) = self.memory.filter(|m| m.total > 0) {
+            push_sample(
+                &mut self.memory_history,
+                memory.used as f64 / memory.total as f64,
+            );
+        }
+        self.load_average = files.read("proc/loadavg").and_then(parse_loadavg);
+        if let Some([load, _, _]) = self.load_average {
+            // a load of one per cpu is a full sparkline
+            push_sample(&mut self.load_history, load / self.cpu_count.max(1) as f64);
+        }
+        self.uptime = files.read("proc/uptime").and_then(parse_uptime);
+    }
+
+    pub fn render_cpu(
+        &self,
+        display: Display,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let usage = *self.cpu.back()?;
+        let text = match display {
+            Display::Text => format!("CPU {:.0}%", usage * 100.0),
+            Display::Sparkline => format!("CPU {}", sparkline(&self.cpu)),
+        };
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+
+    pub fn render_memory(
+        &self,
+        display: Display,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let memory = self.memory?;
+        let text = match display {
+            Display::Text => format!(
+                "MEM {}/{}",
+                format_gigabytes(memory.used),
+                format_gigabytes(memory.total)
+            ),
+            Display::Sparkline => format!("MEM {}", sparkline(&self.memory_history)),
+        };
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+
+    pub fn render_load(
+        &self,
+        display: Display,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let [one, five, fifteen] = self.load_average?;
+        let text = match display {
+            Display::Text => format!("{:.2} {:.2} {:.2}", one, five, fifteen),
+            Display::Sparkline => format!("{:.2} {}", one, sparkline(&self.load_history)),
+        };
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+
+    // the uptime only changes one way, so it's always text
+    pub fn render_uptime(&self, palette: Styling, separator: &str) -> Option<LinePart> {
+        let text = format!("up {}", format_duration(self.uptime?));
+        Some(render(
+            &text,
+            palette.ribbon_unselected.background,
+            palette,
+            separator,
+        ))
+    }
+}
+
+// where an absolute host path is in the plugin filesystem, which has the host folder at /host
+pub fn host_path(host_folder: &Path, path: &Path) -> PathBuf {
+    Path::new("/host").join(path.strip_prefix(host_folder).unwrap_or(path))
+}
+
+// the deepest folder containing all the files, relative paths are left out
+pub fn common_folder<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut folder: Option<PathBuf> = None;
+    for path in paths.into_iter().filter(|p| p.is_absolute()) {
+        let parent = path.parent().unwrap_or(path);
+        match &mut folder {
+            Some(folder) => while !parent.starts_with(&folder) && folder.pop() {},
+            None => folder = Some(parent.to_path_buf()),
+        }
+    }
+    folder
+}
+
+// whether the last sample is older than interval, in which case now becomes the last sample
+pub fn is_due(sampled_at: &mut Option<Instant>, now: Instant, interval: Duration) -> bool {
+    if sampled_at.is_some_and(|at| now.saturating_duration_since(at) < interval) {
+        return false;
+    }
+    *sampled_at = Some(now);
+    true
+}
+
+// the output of the files command, every file is a separator and its path on one line, then
+// its contents
+fn parse_files(output: &str) -> BTreeMap<String, String> {
+    output
+        .split(FILE_SEPARATOR)
+        .filter_map(|file| {
+            let (path, contents) = file.split_once('\n')?;
+            Some((path.to_string(), contents.to_string()))
+        })
+        .collect()
+}
+
+fn push_sample(history: &mut VecDeque<f64>, value: f64) {
+    history.push_back(value.clamp(0.0, 1.0));
+    while history.len() > HISTORY_SIZE {
+        history.pop_front();
+    }
+}
+
+// one block per value, values are from 0 to 1
+pub fn sparkline<'a>(values: impl IntoIterator<Item = &'a f64>) -> String {
+    values
+        .into_iter()
+        .map(|v| {
+            let index = (v.clamp(0.0, 1.0) * (SPARKLINE_BLOCKS.len() - 1) as f64).round();
+            SPARKLINE_BLOCKS[index as usize]
+        })
+        .collect()
+}
+
+pub fn render(text: &str, bg: PaletteColor, palette: Styling, separator: &str) -> LinePart {
+    let part = format!("{} {} ", separator, text);
+    let len = part.width();
+
+    let fg = palette.text_unselected.background;
+    let part = style!(fg, bg).bold().paint(part).to_string();
+
+    LinePart {
+        part,
+        len,
+        tab_index: None,
+        segment: None,
+    }
+}
+
+// the first line of /proc/stat adds up all cpus, the following `cpuN` lines are one per cpu
+fn parse_stat(stat: &str) -> Option<(CpuTimes, usize)> {
+    let mut lines = stat.lines();
+    let mut fields = lines.next()?.split_whitespace();
+    if fields.next() != Some("cpu") {
+        return None;
+    }
+    // user nice system idle iowait irq softirq steal, guest time is already part of user time
+    let times: Vec<u64> = fields.take(8).filter_map(|f| f.parse().ok()).collect();
+    if times.len() < 4 {
+        return None;
+    }
+    let total: u64 = times.iter().sum();
+    let idle = times[3] + times.get(4).copied().unwrap_or(0);
+    let cpu_count = lines
+        .take_while(|l| l.starts_with("cpu"))
+        .filter(|l| l.as_bytes().get(3).is_some_and(u8::is_ascii_digit))
+        .count();
+    Some((
+        CpuTimes {
+            busy: total - idle,
+            total,
+        },
+        cpu_count,
+    ))
+}
+
+// used memory is what isn't available, caches that can be dropped don't count
+fn parse_meminfo(meminfo: &str) -> Option<Memory> {
+    let field = |name: &str| {
+        meminfo.lines().find_map(|l| {
+            let value = l.strip_prefix(name)?.strip_prefix(':')?;
+            value.split_whitespace().next()?.parse::<u64>().ok()
+        })
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable").or_else(|| {
+        // kernels before 3.14 don't have MemAvailable
+        Some(field("MemFree")? + field("Buffers").unwrap_or(0) + field("Cached").unwrap_or(0))
+    })?;
+    Some(Memory {
+        used: total.saturating_sub(available),
+        total,
+    })
+}
+
+fn parse_loadavg(loadavg: &str) -> Option<[f64; 3]> {
+    let mut fields = loadavg.split_whitespace().map(|f| f.parse().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+// the first number is the uptime in seconds, the second one the idle time
+fn parse_uptime(uptime: &str) -> Option<Duration> {
+    let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Duration::try_from_secs_f64(seconds.max(0.0)).ok()
+}
+
+fn format_gigabytes(kilobytes: u64) -> String {
+    format!("{:.1}G", kilobytes as f64 / (1024.0 * 1024.0))
+}
+
+// the two largest units, eg. `3d 4h` or `5m`
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_folder() {
+        let paths = [
+            Path::new("/home/me/.kube/config"),
+            Path::new("/home/me/calendars/work.ics"),
+            Path::new("relative.txt"),
+        ];
+        let folder = common_folder(paths).unwrap();
+        assert_eq!(folder, Path::new("/home/me"));
+        assert_eq!(
+            host_path(&folder, paths[1]),
+            Path::new("/host/calendars/work.ics")
+        );
+        assert_eq!(
+            common_folder([Path::new("/"), paths[0]]),
+            Some(PathBuf::from("/"))
+        );
+        assert_eq!(
+            common_folder([Path::new("/etc/hostname")]),
+            Some(PathBuf::from("/etc"))
+        );
+        assert_eq!(common_folder([Path::new("relative.txt")]), None);
+    }
+
+    #[test]
+    fn stat() {
+        let stat = "cpu  100 5 50 800 20 3 2 0 10 0\n\
+                    cpu0 50 2 25 400 10 1 1 0 5 0\n\
+                    cpu1 50 3 25 400 10 2 1 0 5 0\n\
+                    intr 12345\n";
+        let (times, cpu_count) = parse_stat(stat).unwrap();
+        assert_eq!((times.busy, times.total), (160, 980));
+        assert_eq!(cpu_count, 2);
+        assert!(parse_stat("intr 12345\n").is_none());
+    }
+
+    #[test]
+    fn meminfo() {
+        let memory = parse_meminfo("MemTotal: 16000 kB\nMemFree: 2000 kB\nMemAvailable: 6000 kB\n");
+        let memory = memory.unwrap();
+        assert_eq!((memory.used, memory.total), (10000, 16000));
+        // without MemAvailable, buffers and caches count as free
+        let memory = parse_meminfo(
+            "MemTotal: 16000 kB\nMemFree: 2000 kB\nBuffers: 1000 kB\nCached: 3000 kB\n",
+        );
+        assert_eq!(memory.unwrap().used, 10000);
+        assert!(parse_meminfo("MemFree: 2000 kB\n").is_none());
+    }
+
+    #[test]
+    fn loadavg() {
+        assert_eq!(
+            parse_loadavg("0.52 0.58 0.59 1/467 12345\n"),
+            Some([0.52, 0.58, 0.59])
+        );
+        assert_eq!(parse_loadavg("0.52 0.58"), None);
+    }
+
+    #[test]
+    fn uptime() {
+        assert_eq!(
+            parse_uptime("350735.47 234388.90\n"),
+            Some(Duration::from_secs_f64(350735.47))
+        );
+        assert_eq!(parse_uptime("1e30 0"), None);
+        assert_eq!(parse_uptime(""), None);
+    }
+}
@@ -21,6 +21,7 @@ pub fn render_tab(
     text: String,
     tab: &TabInfo,
     is_alternate_tab: bool,
+    markers: TabMarkers,
     palette: Styling,
     separator: &str,
 ) -> LinePart {
@@ -47,9 +48,17 @@ pub fn render_tab(
     let left_separator = style!(separator_fill_color, background_color).paint(separator);
     let mut tab_text_len = text.width() + (separator_width * 2) + 2; // + 2 for padding
 
-    let tab_styled_text = style!(foreground_color, background_color)
-        .bold()
-        .paint(format!(" {} ", text));
+    let mut text_style = style!(foreground_color, background_color).bold();
+    if markers.is_previous {
+        text_style = text_style.underline();
+    }
+    // the drop marker takes the place of the padding, so the tabs don't move while dragging
+    let (left_padding, right_padding) = match markers.drop_marker {
+        Some(DropMarker::Before) => ('▏', ' '),
+        Some(DropMarker::After) => (' ', '▕'),
+        None => (' ', ' '),
+    };
+    let tab_styled_text = text_style.paint(format!("{}{}{}", left_padding, text, right_padding));
 
     let right_separator = style!(background_color, separator_fill_color).paint(separator);
     let tab_styled_text = if !focused_clients.is_empty() {
@@ -81,13 +90,30 @@ pub fn render_tab(
         part: tab_styled_text,
         len: tab_text_len,
         tab_index: Some(tab.position),
+        segment: None,
     }
 }
 
+// where a dragged tab will be dropped relative to the tab under the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropMarker {
+    Before,
+    After,
+}
+
+// state of the plugin that's shown on a tab
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TabMarkers {
+    pub is_pending_close: bool,
+    pub drop_marker: Option<DropMarker>,
+    pub is_previous: bool,
+}
+
 pub fn tab_style(
     mut tabname: String,
     tab: &TabInfo,
     mut is_alternate_tab: bool,
+    markers: TabMarkers,
     palette: Styling,
     capabilities: PluginCapabilities,
 ) -> LinePart {
@@ -98,12 +124,15 @@ pub fn tab_style(
     } else if tab.is_sync_panes_active {
         tabname.push_str(" *S");
     }
+    if markers.is_pending_close {
+        tabname.push_str(" close?");
+    }
     // we only color alternate tabs differently if we can't use the arrow fonts to separate them
     if !capabilities.arrow_fonts {
         is_alternate_tab = false;
     }
 
-    render_tab(tabname, tab, is_alternate_tab, palette, separator)
+    render_tab(tabname, tab, is_alternate_tab, markers, palette, separator)
 }
 
 pub(crate) fn get_tab_to_focus(
@@ -121,6 +150,15 @@ pub(crate) fn get_tab_to_focus(
     None
 }
 
+// the position of the tab under the mouse, ignoring the collapsed tabs indicators
+pub(crate) fn get_clicked_tab(tab_line: &[LinePart], mouse_click_col: usize) -> Option<usize> {
+    let clicked_line_part = get_clicked_line_part(tab_line, mouse_click_col)?;
+    if clicked_line_part.segment.is_some() {
+        return None;
+    }
+    clicked_line_part.tab_index
+}
+
 pub(crate) fn get_clicked_line_part(
     tab_line: &[LinePart],
     mouse_click_col: usize,
@@ -0,0 +1,83 @@
+use zellij_tile::prelude::*;
+
+use crate::system::{render, SystemFiles, Thresholds};
+use crate::LinePart;
+
+const THERMAL: &str = "sys/class/thermal";
+// the files read from every zone
+pub const ZONE_FILES: [&str; 2] = [
+    "sys/class/thermal/thermal_zone*/type",
+    "sys/class/thermal/thermal_zone*/temp",
+];
+
+#[derive(Debug, Default)]
+pub struct Thermal {
+    // in °C
+    temperature: Option<f64>,
+}
+
+impl Thermal {
+    pub fn sample(&mut self, files: &SystemFiles, zone_type: Option<&str>) {
+        self.temperature = read_temperature(files, zone_type);
+    }
+
+    pub fn render(
+        &self,
+        thresholds: Thresholds,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let temperature = self.temperature?;
+        let text = format!("{:.0}°C", temperature);
+        Some(render(
+            &text,
+            thresholds.color(temperature, palette),
+            palette,
+            separator,
+        ))
+    }
+}
+
+// the hottest zone of zone_type, or of all zones, zone temperatures are in millidegrees
+fn read_temperature(files: &SystemFiles, zone_type: Option<&str>) -> Option<f64> {
+    let read = |zone: &str, name: &str| files.read(&format!("{}/{}/{}", THERMAL, zone, name));
+    files
+        .folders(THERMAL)
+        .into_iter()
+        .filter(|zone| zone.starts_with("thermal_zone"))
+        .filter(|zone| match zone_type {
+            Some(zone_type) => read(zone, "type").map(str::trim) == Some(zone_type),
+            None => true,
+        })
+        .filter_map(|zone| read(zone, "temp")?.trim().parse::<f64>().ok())
+        .map(|millidegrees| millidegrees / 1000.0)
+        .max_by(f64::total_cmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hottest_zone() {
+        let zones = [
+            ("thermal_zone0", "acpitz", "45000"),
+            ("thermal_zone1", "x86_pkg_temp", "61500"),
+            ("thermal_zone2", "iwlwifi_1", "38000"),
+            ("cooling_device0", "Processor", "99000"),
+        ];
+        // as printed by the files command
+        let mut output = String::new();
+        for (zone, zone_type, temp) in zones {
+            output.push_str(&format!(
+                "\u{1e}{}/{}/type\n{}\n\u{1e}{}/{}/temp\n{}\n",
+                THERMAL, zone, zone_type, THERMAL, zone, temp
+            ));
+        }
+        let mut files = SystemFiles::default();
+        files.handle_result(Some(0), output.as_bytes(), &[]);
+        assert_eq!(read_temperature(&files, None), Some(61.5));
+        assert_eq!(read_temperature(&files, Some("acpitz")), Some(45.0));
+        assert_eq!(read_temperature(&files, Some("nvme")), None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::time::{Duration, Instant};
+
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
+
+use crate::{LinePart, Segment};
+
+// expired countdowns flash for this long before they're removed, unless they're clicked
+const EXPIRED_FOR: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimerAction {
+    // a countdown of the given length, or a stopwatch
+    Start {
+        length: Option<Duration>,
+        label: Option<String>,
+    },
+    // the timers matching the selector are removed, or paused and resumed
+    Stop(TimerSelector),
+    Pause(TimerSelector),
+    List,
+}
+
+// all the timers when both are None
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TimerSelector {
+    pub id: Option<usize>,
+    pub label: Option<String>,
+}
+
+impl TimerSelector {
+    fn matches(&self, timer: &Timer) -> bool {
+        self.id.is_none_or(|id| id == timer.id)
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|label| timer.label.as_ref() == Some(label))
+    }
+}
+
+#[derive(Debug)]
+struct Timer {
+    id: usize,
+    label: Option<String>,
+    // none for stopwatches
+    length: Option<Duration>,
+    started_at: Instant,
+    // time spent paused, up to the current pause
+    paused_for: Duration,
+    paused_at: Option<Instant>,
+    expired_at: Option<Instant>,
+}
+
+impl Timer {
+    fn elapsed(&self, now: Instant) -> Duration {
+        let until = self.paused_at.unwrap_or(now);
+        until
+            .saturating_duration_since(self.started_at)
+            .saturating_sub(self.paused_for)
+    }
+
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.length
+            .map(|length| length.saturating_sub(self.elapsed(now)))
+    }
+
+    fn name(&self) -> String {
+        match (&self.label, self.length) {
+            (Some(label), _) => label.clone(),
+            (None, Some(length)) => format_length(length),
+            (None, None) => "stopwatch".to_string(),
+        }
+    }
+
+    fn text(&self, now: Instant) -> String {
+        let icon = if self.length.is_some() { "⏳" } else { "⏱" };
+        let time = if self.expired_at.is_some() {
+            "done".to_string()
+        } else {
+            match self.remaining(now) {
+                // rounded up, so countdowns show 0:00 only when they're done
+                Some(remaining) => format_duration(remaining.as_millis().div_ceil(1000) as u64),
+                None => format_duration(self.elapsed(now).as_secs()),
+            }
+        };
+        let mut text = match &self.label {
+            Some(label) => format!("{} {} {}", icon, label, time),
+            None => format!("{} {}", icon, time),
+        };
+        if self.paused_at.is_some() {
+            text.push_str(" ⏸");
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+    next_id: usize,
+}
+
+impl Timers {
+    pub fn start(&mut self, now: Instant, length: Option<Duration>, label: Option<String>) {
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id: self.next_id,
+            label,
+            length,
+            started_at: now,
+            paused_for: Duration::ZERO,
+            paused_at: None,
+            expired_at: None,
+        });
+    }
+
+    // returns how many timers were removed
+    pub fn stop(&mut self, selector: &TimerSelector) -> usize {
+        let count = self.timers.len();
+        self.timers.retain(|timer| !selector.matches(timer));
+        count - self.timers.len()
+    }
+
+    // pauses or resumes the matching timers, returns how many there were
+    pub fn toggle_pause(&mut self, now: Instant, selector: &TimerSelector) -> usize {
+        let mut count = 0;
+        for timer in &mut self.timers {
+            // expired countdowns have nothing left to pause
+            if !selector.matches(timer) || timer.expired_at.is_some() {
+                continue;
+            }
+            match timer.paused_at.take() {
+                Some(paused_at) => timer.paused_for += now.saturating_duration_since(paused_at),
+                None => timer.paused_at = Some(now),
+            }
+            count += 1;
+        }
+        count
+    }
+
+    // clicking a timer that expired dismisses it
+    pub fn click(&mut self, now: Instant, id: usize) {
+        let selector = TimerSelector {
+            id: Some(id),
+            label: None,
+        };
+        if self
+            .timers
+            .iter()
+            .any(|t| t.id == id && t.expired_at.is_some())
+        {
+            self.stop(&selector);
+        } else {
+            self.toggle_pause(now, &selector);
+        }
+    }
+
+    // returns the names of the countdowns that just expired, and removes the ones that expired a
+    // while ago
+    pub fn tick(&mut self, now: Instant) -> Vec<String> {
+        let mut expired = vec![];
+        for timer in &mut self.timers {
+            if timer.expired_at.is_none() && timer.remaining(now) == Some(Duration::ZERO) {
+                timer.expired_at = Some(now);
+                expired.push(timer.name());
+            }
+        }
+        self.timers.retain(|timer| {
+            timer
+                .expired_at
+                .is_none_or(|at| now.saturating_duration_since(at) < EXPIRED_FOR)
+        });
+        expired
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    // one timer per line, eg. `2 tea 3:12`
+    pub fn list(&self, now: Instant) -> String {
+        self.timers
+            .iter()
+            .map(|timer| format!("{} {}", timer.id, timer.text(now)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // the timers, each in its own part, in the order they were started
+    pub fn render(&self, now: Instant, palette: Styling, separator: &str) -> Vec<LinePart> {
+        self.timers
+            .iter()
+            .map(|timer| render_timer(timer, &timer.text(now), now, palette, separator))
+            .collect()
+    }
+
+    // the timer that needs attention the soonest, with a count of the others: expired countdowns,
+    // then the countdown ending first, then the last stopwatch started
+    pub fn render_collapsed(
+        &self,
+        now: Instant,
+        palette: Styling,
+        separator: &str,
+    ) -> Option<LinePart> {
+        let soonest = self
+            .timers
+            .iter()
+            .filter(|t| t.expired_at.is_some())
+            .chain(
+                self.timers
+                    .iter()
+                    .filter(|t| t.length.is_some() && t.paused_at.is_none())
+                    .min_by_key(|t| t.remaining(now)),
+            )
+            .chain(self.timers.iter().rev())
+            .next()?;
+        let mut text = soonest.text(now);
+        if self.timers.len() > 1 {
+            text.push_str(&format!(" +{}", self.timers.len() - 1));
+        }
+        Some(render_timer(soonest, &text, now, palette, separator))
+    }
+}
+
+fn render_timer(
+    timer: &Timer,
+    text: &str,
+    now: Instant,
+    palette: Styling,
+    separator: &str,
+) -> LinePart {
+    let part = format!("{} {} ", separator, text);
+    let len = part.width();
+
+    let fg = palette.text_unselected.background;
+    let bg = palette.ribbon_selected.background;
+    // expired countdowns flash in red until they're dismissed
+    let part = match timer.expired_at {
+        Some(at) if (now.saturating_duration_since(at).as_millis() / 500).is_multiple_of(2) => {
+            style!(fg, palette.exit_code_error.base)
+                .bold()
+                .paint(part)
+                .to_string()
+        }
+        Some(_) => style!(palette.exit_code_error.base, fg)
+            .bold()
+            .paint(part)
+            .to_string(),
+        None => style!(fg, bg).bold().paint(part).to_string(),
+    };
+
+    LinePart {
+        part,
+        len,
+        tab_index: None,
+        segment: Some(Segment::Timer(timer.id)),
+    }
+}
+
+// eg. `90s`, `4m`, `1h30m` or `1.5h`, plain numbers are seconds
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return None,
+        };
+        seconds += number.parse::<f64>().ok()? * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        seconds += number.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+        .filter(|s| *s > 0.0)
+        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+}
+
+// the length of a countdown without a label, eg. `4m` or `1h30m`
+fn format_length(length: Duration) -> String {
+    let seconds = length.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let mut text = String::new();
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        text.push_str(&format!("{}m", minutes));
+    }
+    if seconds > 0 || text.is_empty() {
+        text.push_str(&format!("{}s", seconds));
+    }
+    text
+}
+
+// `m:ss`, or `h:mm:ss` from an hour up
+fn format_duration(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1.5h"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("4d"), None);
+        assert_eq!(parse_duration("99999999999999999999999"), None);
+        assert_eq!(parse_duration("99999999999999999999999h"), None);
+    }
+}